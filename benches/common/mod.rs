@@ -0,0 +1,34 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Helpers shared by the benchmarks.
+
+use std::time::Duration;
+
+use woxml::XmlWriter;
+
+pub const SAMPLES: usize = 100;
+pub const ITERATIONS: usize = 100;
+pub const DURATION: Duration = Duration::from_secs(5);
+
+/// Write a small document with namespaces, comments, attributes, text and CDATA.
+pub fn create_xml(writer: &mut XmlWriter<'_, impl woxml::Write>, nsmap: &Vec<(Option<&'static str>, &'static str)>) {
+	_ = writer.begin_elem("OTDS");
+	_ = writer.ns_decl(nsmap);
+	_ = writer.comment("have a nice day");
+	writer.set_namespace("st");
+	_ = writer.empty_elem("success");
+	_ = writer.begin_elem("node");
+	_ = writer.attr_esc("name", "\"123\"");
+	_ = writer.attr("id", "abc");
+	_ = writer.attr("'unescaped'", "\"123\""); // this WILL generate invalid xml
+	_ = writer.text("'text'");
+	_ = writer.end_elem();
+	writer.unset_namespace();
+	_ = writer.comment("comment");
+	_ = writer.begin_elem("stuff");
+	_ = writer.cdata("blablab");
+	_ = writer.end_elem();
+	_ = writer.end_elem();
+	_ = writer.close();
+	_ = writer.flush();
+}
@@ -0,0 +1,160 @@
+// Copyright © 2026 Stephan Kunz
+#![allow(missing_docs)]
+
+//! Benchmark comparisons of woxml with `quick-xml` and `xml-rs`
+
+mod common;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use woxml::XmlWriter;
+
+use common::{DURATION, ITERATIONS, SAMPLES, create_xml};
+
+/// Write the `create_xml` document with `quick-xml`.
+fn create_xml_quick_xml<W: std::io::Write>(writer: &mut quick_xml::Writer<W>) {
+	use quick_xml::events::{BytesCData, BytesEnd, BytesStart, BytesText, Event};
+
+	let mut root = BytesStart::new("OTDS");
+	root.push_attribute(("xmlns", "http://localhost/"));
+	root.push_attribute(("xmlns:st", "http://127.0.0.1/"));
+	_ = writer.write_event(Event::Start(root));
+	_ = writer.write_event(Event::Comment(BytesText::from_escaped(" have a nice day ")));
+	_ = writer.write_event(Event::Empty(BytesStart::new("st:success")));
+	let mut node = BytesStart::new("st:node");
+	node.push_attribute(("name", "\"123\""));
+	node.push_attribute(("id", "abc"));
+	node.push_attribute(("'unescaped'", "\"123\""));
+	_ = writer.write_event(Event::Start(node));
+	_ = writer.write_event(Event::Text(BytesText::new("'text'")));
+	_ = writer.write_event(Event::End(BytesEnd::new("st:node")));
+	_ = writer.write_event(Event::Comment(BytesText::from_escaped(" comment ")));
+	_ = writer.write_event(Event::Start(BytesStart::new("stuff")));
+	_ = writer.write_event(Event::CData(BytesCData::new("blablab")));
+	_ = writer.write_event(Event::End(BytesEnd::new("stuff")));
+	_ = writer.write_event(Event::End(BytesEnd::new("OTDS")));
+}
+
+/// Write the `create_xml` document with `xml-rs`.
+fn create_xml_xml_rs<W: std::io::Write>(writer: &mut xml::EventWriter<W>) {
+	use xml::writer::XmlEvent;
+
+	_ = writer.write(
+		XmlEvent::start_element("OTDS")
+			.default_ns("http://localhost/")
+			.ns("st", "http://127.0.0.1/"),
+	);
+	_ = writer.write(XmlEvent::comment("have a nice day"));
+	_ = writer.write(XmlEvent::start_element("st:success"));
+	_ = writer.write(XmlEvent::end_element());
+	_ = writer.write(
+		XmlEvent::start_element("st:node")
+			.attr("name", "\"123\"")
+			.attr("id", "abc")
+			.attr("'unescaped'", "\"123\""),
+	);
+	_ = writer.write(XmlEvent::characters("'text'"));
+	_ = writer.write(XmlEvent::end_element());
+	_ = writer.write(XmlEvent::comment("comment"));
+	_ = writer.write(XmlEvent::start_element("stuff"));
+	_ = writer.write(XmlEvent::cdata("blablab"));
+	_ = writer.write(XmlEvent::end_element());
+	_ = writer.write(XmlEvent::end_element());
+}
+
+/// A large text with some characters to escape
+fn large_text() -> String {
+	"Lorem ipsum dolor sit amet, <consectetur> adipiscing elit & 'sed' do eiusmod tempor. ".repeat(1000)
+}
+
+/// Comparison of woxml with the writers of `quick-xml` and `xml-rs`,
+/// each writing `ITERATIONS` documents into a fresh buffer.
+fn compare(c: &mut Criterion) {
+	let nsmap: Vec<(Option<&'static str>, &'static str)> = vec![
+		(None, "http://localhost/"),
+		(Some("st"), "http://127.0.0.1/"),
+	];
+
+	let mut group = c.benchmark_group("compare");
+	group
+		.measurement_time(DURATION)
+		.sample_size(SAMPLES);
+	group.bench_function("woxml", |b| {
+		b.iter(|| {
+			let mut writer = XmlWriter::compact_mode(Vec::new());
+			for _ in 1..=ITERATIONS {
+				create_xml(&mut writer, &nsmap);
+			}
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.bench_function("quick-xml", |b| {
+		b.iter(|| {
+			let mut writer = quick_xml::Writer::new(Vec::new());
+			for _ in 1..=ITERATIONS {
+				create_xml_quick_xml(&mut writer);
+			}
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.bench_function("xml-rs", |b| {
+		b.iter(|| {
+			let mut buffer = Vec::new();
+			for _ in 1..=ITERATIONS {
+				// xml-rs does not allow more than one root element per writer
+				let mut writer = xml::EmitterConfig::new()
+					.write_document_declaration(false)
+					.create_writer(&mut buffer);
+				create_xml_xml_rs(&mut writer);
+			}
+			std::hint::black_box(buffer);
+		});
+	});
+	group.finish();
+}
+
+/// Comparison of woxml with the writers of `quick-xml` and `xml-rs` for a large text.
+fn compare_large_text(c: &mut Criterion) {
+	let text = large_text();
+	let mut group = c.benchmark_group("compare_large_text");
+	group
+		.measurement_time(DURATION)
+		.sample_size(SAMPLES);
+	group.bench_function("woxml", |b| {
+		b.iter(|| {
+			let mut writer = XmlWriter::compact_mode(Vec::new());
+			_ = writer.begin_elem("text");
+			_ = writer.text(&text);
+			_ = writer.end_elem();
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.bench_function("quick-xml", |b| {
+		use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+
+		b.iter(|| {
+			let mut writer = quick_xml::Writer::new(Vec::new());
+			_ = writer.write_event(Event::Start(BytesStart::new("text")));
+			_ = writer.write_event(Event::Text(BytesText::new(&text)));
+			_ = writer.write_event(Event::End(BytesEnd::new("text")));
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.bench_function("xml-rs", |b| {
+		use xml::writer::XmlEvent;
+
+		b.iter(|| {
+			let mut writer = xml::EmitterConfig::new()
+				.write_document_declaration(false)
+				.create_writer(Vec::new());
+			_ = writer.write(XmlEvent::start_element("text"));
+			_ = writer.write(XmlEvent::characters(&text));
+			_ = writer.write(XmlEvent::end_element());
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.finish();
+}
+
+criterion_group!(benches, compare, compare_large_text);
+
+criterion_main!(benches);
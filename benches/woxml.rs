@@ -6,36 +6,12 @@
 #[doc(hidden)]
 extern crate alloc;
 
-use std::time::Duration;
+mod common;
 
 use criterion::{Criterion, criterion_group, criterion_main};
 use woxml::XmlWriter;
 
-const SAMPLES: usize = 100;
-const ITERATIONS: usize = 100;
-const DURATION: Duration = Duration::from_secs(5);
-
-fn create_xml(writer: &mut XmlWriter<'_, impl woxml::Write>, nsmap: &Vec<(Option<&'static str>, &'static str)>) {
-	_ = writer.begin_elem("OTDS");
-	_ = writer.ns_decl(nsmap);
-	_ = writer.comment("have a nice day");
-	writer.set_namespace("st");
-	_ = writer.empty_elem("success");
-	_ = writer.begin_elem("node");
-	_ = writer.attr_esc("name", "\"123\"");
-	_ = writer.attr("id", "abc");
-	_ = writer.attr("'unescaped'", "\"123\""); // this WILL generate invalid xml
-	_ = writer.text("'text'");
-	_ = writer.end_elem();
-	writer.unset_namespace();
-	_ = writer.comment("comment");
-	_ = writer.begin_elem("stuff");
-	_ = writer.cdata("blablab");
-	_ = writer.end_elem();
-	_ = writer.end_elem();
-	_ = writer.close();
-	_ = writer.flush();
-}
+use common::{DURATION, ITERATIONS, SAMPLES, create_xml};
 
 fn woxml(c: &mut Criterion) {
 	let mut group = c.benchmark_group("woxml");
@@ -79,6 +55,29 @@ fn woxml(c: &mut Criterion) {
 	});
 }
 
-criterion_group!(benches, woxml);
+/// Plain start and end tags, guarding the per write overhead of the writer
+fn begin_end_elem(c: &mut Criterion) {
+	let mut group = c.benchmark_group("begin_end_elem");
+	group
+		.measurement_time(DURATION)
+		.sample_size(SAMPLES);
+	group.bench_function("compact", |b| {
+		b.iter(|| {
+			let mut writer = XmlWriter::compact_mode(Vec::new());
+			_ = writer.begin_elem("root");
+			for _ in 1..=ITERATIONS {
+				_ = writer.begin_elem("node");
+				_ = writer.begin_elem("leaf");
+				_ = writer.end_elem();
+				_ = writer.end_elem();
+			}
+			_ = writer.end_elem();
+			std::hint::black_box(writer.into_inner());
+		});
+	});
+	group.finish();
+}
+
+criterion_group!(benches, woxml, begin_end_elem);
 
 criterion_main!(benches);
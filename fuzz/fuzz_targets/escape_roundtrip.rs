@@ -0,0 +1,76 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Fuzzing of the escaping: arbitrary text and attribute values are written,
+//! parsed again with a real XML parser and have to match the original.
+//!
+//! Run with `cargo fuzz run escape_roundtrip` from the repository root.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quick_xml::{Reader, XmlVersion, events::Event};
+use woxml::{Config, Quote, XmlWriter};
+
+/// Returns `true` for characters matching the XML `Char` production.
+const fn is_xml_char(c: char) -> bool {
+	matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// Parse `xml`, returning the value of the first attribute and the text content.
+fn parse(xml: &str) -> (String, String) {
+	let mut reader = Reader::from_str(xml);
+	let mut value = None;
+	let mut text = String::new();
+	loop {
+		match reader.read_event() {
+			Ok(Event::Start(elem) | Event::Empty(elem)) => {
+				let attr = elem.attributes().next().unwrap().unwrap();
+				value = Some(
+					attr.normalized_value(XmlVersion::Implicit1_0)
+						.unwrap()
+						.into_owned(),
+				);
+			}
+			Ok(Event::Text(content)) => text.push_str(&content.xml10_content()),
+			Ok(Event::GeneralRef(reference)) => match &*reference.xml10_content() {
+				"amp" => text.push('&'),
+				"lt" => text.push('<'),
+				"gt" => text.push('>'),
+				"apos" => text.push('\''),
+				"quot" => text.push('"'),
+				_ => text.push(reference.resolve_char_ref().unwrap().unwrap()),
+			},
+			Ok(Event::Eof) => break,
+			Ok(_) => {}
+			Err(err) => panic!("not well-formed ({err}): {xml}"),
+		}
+	}
+	(value.unwrap(), text)
+}
+
+/// Write `value` as attribute value and `text` as text content with `config`.
+fn write(config: Config, value: &str, text: &str) -> String {
+	let mut xml = XmlWriter::with_config(Vec::new(), config);
+	xml.begin_elem("root").unwrap();
+	xml.attr_esc("value", value).unwrap();
+	xml.text(text).unwrap();
+	xml.end_elem().unwrap();
+	String::try_from(xml).unwrap()
+}
+
+fuzz_target!(|input: (bool, &str, &str)| {
+	let (single, value, text) = input;
+	if !value.chars().chain(text.chars()).all(is_xml_char) {
+		return;
+	}
+
+	// canonical output escapes all whitespace, so everything has to survive
+	let xml = write(Config::canonical(), value, text);
+	assert_eq!(parse(&xml), (value.to_owned(), text.to_owned()), "{xml}");
+
+	// otherwise a parser normalizes whitespace in attribute values and line endings in text
+	let quote = if single { Quote::Single } else { Quote::Double };
+	let value = value.replace(['\t', '\n', '\r'], " ");
+	let text = text.replace("\r\n", "\n").replace('\r', "\n");
+	let xml = write(Config::compact().with_quote(quote), &value, &text);
+	assert_eq!(parse(&xml), (value, text), "{xml}");
+});
@@ -0,0 +1,95 @@
+// Copyright © 2026 Stephan Kunz
+//! Bridge from the synchronous [`XmlWriter`](crate::XmlWriter) to asynchronous IO, see [`AsyncBufferedWriter`].
+
+use alloc::vec::Vec;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{error::Error, write::Write};
+
+/// An in-memory buffer to be written by an [`XmlWriter`](crate::XmlWriter),
+/// which can be drained to a [`tokio::io::AsyncWrite`] sink with [`AsyncBufferedWriter::flush_to`].
+///
+/// Serialization itself remains synchronous, only the transfer of the buffered output to the sink is asynchronous.
+/// The buffer is usually taken from the writer with [`XmlWriter::take_buffer`](crate::XmlWriter::take_buffer):
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), woxml::Error> {
+/// use woxml::{AsyncBufferedWriter, XmlWriter};
+///
+/// let mut sink = Vec::new();
+/// let mut xml = XmlWriter::compact_mode(AsyncBufferedWriter::new());
+/// xml.begin_elem("log")?;
+/// xml.empty_elem("entry")?;
+/// xml.take_buffer().flush_to(&mut sink).await?;
+/// xml.end_elem()?;
+/// xml.flush()?;
+/// xml.take_buffer().flush_to(&mut sink).await?;
+/// assert_eq!(sink, b"<log><entry/></log>");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AsyncBufferedWriter {
+	buffer: Vec<u8>,
+}
+
+impl AsyncBufferedWriter {
+	/// Create an empty buffer.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { buffer: Vec::new() }
+	}
+
+	/// Create an empty buffer with at least the given capacity.
+	#[must_use]
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			buffer: Vec::with_capacity(capacity),
+		}
+	}
+
+	/// Write the buffered output to `sink`, flush the sink and clear the buffer.
+	/// Returns the number of bytes written.
+	///
+	/// On error the buffer is kept unchanged, so the output may partially have reached the sink.
+	/// # Errors
+	/// - [`Error::Io`] if writing to or flushing the sink fails
+	pub async fn flush_to<S: AsyncWrite + Unpin>(&mut self, mut sink: S) -> Result<usize, Error> {
+		sink.write_all(&self.buffer).await?;
+		sink.flush().await?;
+		let written = self.buffer.len();
+		self.buffer.clear();
+		Ok(written)
+	}
+}
+
+/// [`Write`] implementation for [`AsyncBufferedWriter`], which appends to the in-memory buffer.
+impl Write for AsyncBufferedWriter {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		self.buffer.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	#[inline]
+	fn len(&self) -> Option<usize> {
+		Some(self.buffer.len())
+	}
+
+	#[inline]
+	fn as_bytes(&self) -> Option<&[u8]> {
+		Some(&self.buffer)
+	}
+
+	#[inline]
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		self.buffer.truncate(len);
+		Ok(())
+	}
+}
@@ -0,0 +1,478 @@
+// Copyright © 2026 Stephan Kunz
+//! Configuration of the [`XmlWriter`](crate::XmlWriter).
+
+use core::result::Result;
+
+use crate::error::Error;
+
+/// A check for attribute values, see [`XmlWriter::set_attr_sanitizer`](crate::XmlWriter::set_attr_sanitizer).
+pub type AttrSanitizer = fn(&str) -> Result<(), Error>;
+
+/// An observer of the written output, see [`XmlWriter::set_write_observer`](crate::XmlWriter::set_write_observer).
+pub type WriteObserver = fn(&[u8]);
+
+/// An observer of closed elements, see [`XmlWriter::set_close_observer`](crate::XmlWriter::set_close_observer).
+pub type CloseObserver = fn(&str, usize);
+
+/// The quote delimiting attribute values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quote {
+	/// Double quotes `"`, the default
+	#[default]
+	Double,
+	/// Single quotes `'`
+	Single,
+}
+
+impl Quote {
+	/// The `=` and opening quote of an attribute value
+	pub(crate) const fn open(self) -> &'static str {
+		match self {
+			Self::Double => "=\"",
+			Self::Single => "='",
+		}
+	}
+
+	/// The closing quote of an attribute value
+	pub(crate) const fn close(self) -> &'static str {
+		match self {
+			Self::Double => "\"",
+			Self::Single => "'",
+		}
+	}
+}
+
+/// Spaces for the indentation with [`IndentUnit::Spaces`]
+const SPACES: &str = match core::str::from_utf8(&[b' '; u8::MAX as usize]) {
+	Ok(spaces) => spaces,
+	Err(_) => "",
+};
+
+/// The indentation per level in `pretty` mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentUnit {
+	/// The given number of spaces
+	Spaces(u8),
+	/// A single tab
+	Tab,
+}
+
+impl Default for IndentUnit {
+	fn default() -> Self {
+		Self::Spaces(2)
+	}
+}
+
+impl IndentUnit {
+	/// The indentation string
+	pub(crate) const fn as_str(self) -> &'static str {
+		match self {
+			Self::Spaces(count) => SPACES.split_at(count as usize).0,
+			Self::Tab => "\t",
+		}
+	}
+}
+
+/// The order of namespace declarations relative to the other attributes of an element.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NsOrder {
+	/// Namespace declarations before the other attributes, also if written after them
+	Before,
+	/// Namespace declarations after the other attributes
+	After,
+	/// In the order written, the default.
+	/// With sorted attributes namespace declarations are written first.
+	#[default]
+	AsWritten,
+}
+
+/// The handling of CDATA content containing the end marker `]]>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CdataPolicy {
+	/// Split the CDATA section at the end marker into two sections, the default
+	#[default]
+	Split,
+	/// Write the content as escaped text instead of a CDATA section
+	EscapeAsText,
+	/// Reject the content with [`Error::InvalidCdata`]
+	Error,
+}
+
+/// The escaping of an apostrophe `'` in text and attribute values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApostropheStyle {
+	/// The XML entity `&apos;`, the default
+	#[default]
+	Apos,
+	/// The numeric character reference `&#39;`, which is understood by HTML parsers as well
+	NumericDec,
+	/// No escaping, except in attribute values delimited by [`Quote::Single`], where `&#39;` is used
+	None,
+}
+
+impl ApostropheStyle {
+	/// The escaped apostrophe
+	pub(crate) const fn escaped(self) -> &'static str {
+		match self {
+			Self::Apos => "&apos;",
+			Self::NumericDec | Self::None => "&#39;",
+		}
+	}
+}
+
+/// The configuration of an [`XmlWriter`](crate::XmlWriter), see
+/// [`XmlWriter::with_config`](crate::XmlWriter::with_config).
+/// A configuration can be cloned to create several identically configured writers.
+/// ```
+/// use woxml::{Config, XmlWriter};
+///
+/// let config = Config::pretty().with_indent("\t").with_validate_names(true);
+/// let mut first = XmlWriter::with_config(Vec::new(), config.clone());
+/// let mut second = XmlWriter::with_config(Vec::new(), config);
+/// ```
+#[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+	/// If `true` it will
+	/// - indent all opening elements on a new line
+	/// - put closing elements into own line
+	pub(crate) pretty: bool,
+	/// indentation per level in 'pretty' mode
+	pub(crate) indent: &'static str,
+	/// line ending in 'pretty' mode
+	pub(crate) line_ending: &'static str,
+	/// quote delimiting attribute values
+	pub(crate) quote: Quote,
+	/// escaping of apostrophes
+	pub(crate) apostrophe: ApostropheStyle,
+	/// if `true` only the characters required by the XML specification are escaped
+	pub(crate) strict_escaping: bool,
+	/// if `true` in 'compact' mode sibling nodes are separated by a space
+	pub(crate) compact_spacing: bool,
+	/// if `true` in 'pretty' mode direct children of the root are separated by a blank line
+	pub(crate) blank_line_top_level: bool,
+	/// if `true` in 'pretty' mode a newline is written after the last element closes
+	pub(crate) final_newline: bool,
+	/// if `true` empty text content is ignored
+	pub(crate) empty_text_is_noop: bool,
+	/// separator between consecutive text content of an element
+	pub(crate) text_separator: &'static str,
+	/// if `true` line endings in text and attribute values are normalized
+	pub(crate) normalize_newlines: bool,
+	/// if `true` element and attribute names are validated
+	pub(crate) validate_names: bool,
+	/// if `true` names starting with `xml` are rejected, except for the known reserved names
+	pub(crate) reject_reserved_names: bool,
+	/// if `true` the use of namespace prefixes is checked against their declarations
+	pub(crate) track_namespaces: bool,
+	/// if `true` elements without children are written as `<x></x>`
+	pub(crate) expand_empty: bool,
+	/// if `true` attributes are written sorted when the element tag is closed
+	pub(crate) sort_attributes: bool,
+	/// order of namespace declarations relative to the other attributes
+	pub(crate) ns_order: NsOrder,
+	/// handling of CDATA content containing `]]>`
+	pub(crate) cdata_policy: CdataPolicy,
+	/// if `true` characters are escaped according to canonical XML
+	pub(crate) canonical: bool,
+	/// optional check of attribute values before they are written
+	pub(crate) attr_sanitizer: Option<AttrSanitizer>,
+	/// optional observer of all output before it is handed to the buffer
+	pub(crate) write_observer: Option<WriteObserver>,
+	/// optional observer of each element closed with `end_elem`
+	pub(crate) close_observer: Option<CloseObserver>,
+	/// maximum length of attribute values in bytes
+	pub(crate) max_attr_value_len: usize,
+	/// maximum size of the output in bytes, further output is discarded
+	pub(crate) max_output_bytes: usize,
+	/// bytes reserved within the maximum output size to close open elements on truncation
+	pub(crate) truncation_headroom: usize,
+	/// size of the internal output buffer, `0` if disabled
+	pub(crate) buffer_size: usize,
+	/// if `true` the output is a document, which requires a root element, otherwise a fragment
+	pub(crate) document: bool,
+	/// if `true` the structure of the output is checked when finished
+	pub(crate) validate_on_finish: bool,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self::compact()
+	}
+}
+
+impl Config {
+	/// Configuration for `compact` output, see [`XmlWriter::compact_mode`](crate::XmlWriter::compact_mode).
+	#[must_use]
+	pub const fn compact() -> Self {
+		Self {
+			pretty: false,
+			indent: "  ",
+			line_ending: "\n",
+			quote: Quote::Double,
+			apostrophe: ApostropheStyle::Apos,
+			strict_escaping: false,
+			compact_spacing: false,
+			blank_line_top_level: false,
+			final_newline: false,
+			empty_text_is_noop: true,
+			text_separator: "",
+			normalize_newlines: false,
+			validate_names: false,
+			reject_reserved_names: false,
+			track_namespaces: false,
+			expand_empty: false,
+			sort_attributes: false,
+			ns_order: NsOrder::AsWritten,
+			cdata_policy: CdataPolicy::Split,
+			canonical: false,
+			attr_sanitizer: None,
+			write_observer: None,
+			close_observer: None,
+			max_attr_value_len: usize::MAX,
+			max_output_bytes: usize::MAX,
+			truncation_headroom: 0,
+			buffer_size: 0,
+			document: false,
+			validate_on_finish: false,
+		}
+	}
+
+	/// Configuration for `pretty` output, see [`XmlWriter::pretty_mode`](crate::XmlWriter::pretty_mode).
+	#[must_use]
+	pub const fn pretty() -> Self {
+		Self::compact().with_pretty(true)
+	}
+
+	/// Configuration for `canonical` output, see [`XmlWriter::canonical_mode`](crate::XmlWriter::canonical_mode).
+	#[must_use]
+	pub const fn canonical() -> Self {
+		let mut config = Self::compact()
+			.with_expand_empty_elements(true)
+			.with_sort_attributes(true);
+		config.canonical = true;
+		config
+	}
+
+	/// Enable/disable `pretty` output
+	#[must_use]
+	pub const fn with_pretty(mut self, enabled: bool) -> Self {
+		self.pretty = enabled;
+		self
+	}
+
+	/// Set the indentation per level in `pretty` mode, default is two spaces
+	#[must_use]
+	pub const fn with_indent(mut self, indent: &'static str) -> Self {
+		self.indent = indent;
+		self
+	}
+
+	/// Set the indentation per level in `pretty` mode to spaces or a tab, default is [`IndentUnit::Spaces(2)`](IndentUnit::Spaces)
+	#[must_use]
+	pub const fn with_indent_unit(mut self, unit: IndentUnit) -> Self {
+		self.indent = unit.as_str();
+		self
+	}
+
+	/// Set the line ending in `pretty` mode, default is `\n`
+	#[must_use]
+	pub const fn with_line_ending(mut self, line_ending: &'static str) -> Self {
+		self.line_ending = line_ending;
+		self
+	}
+
+	/// Set the quote delimiting attribute values, default is [`Quote::Double`]
+	#[must_use]
+	pub const fn with_quote(mut self, quote: Quote) -> Self {
+		self.quote = quote;
+		self
+	}
+
+	/// Set the escaping of apostrophes, default is [`ApostropheStyle::Apos`]
+	#[must_use]
+	pub const fn with_apostrophe_escape(mut self, style: ApostropheStyle) -> Self {
+		self.apostrophe = style;
+		self
+	}
+
+	/// Enable/disable escaping of only the characters required by the XML specification,
+	/// see [`XmlWriter::set_strict_escaping`](crate::XmlWriter::set_strict_escaping)
+	#[must_use]
+	pub const fn with_strict_escaping(mut self, enabled: bool) -> Self {
+		self.strict_escaping = enabled;
+		self
+	}
+
+	/// Enable/disable a space between sibling nodes in `compact` mode,
+	/// see [`XmlWriter::set_compact_spacing`](crate::XmlWriter::set_compact_spacing)
+	#[must_use]
+	pub const fn with_compact_spacing(mut self, enabled: bool) -> Self {
+		self.compact_spacing = enabled;
+		self
+	}
+
+	/// Enable/disable blank lines between the direct children of the root element in `pretty` mode
+	#[must_use]
+	pub const fn with_blank_line_between_top_level(mut self, enabled: bool) -> Self {
+		self.blank_line_top_level = enabled;
+		self
+	}
+
+	/// Enable/disable a newline after the last element closes in `pretty` mode
+	#[must_use]
+	pub const fn with_final_newline(mut self, enabled: bool) -> Self {
+		self.final_newline = enabled;
+		self
+	}
+
+	/// Enable/disable ignoring empty text content,
+	/// see [`XmlWriter::set_empty_text_is_noop`](crate::XmlWriter::set_empty_text_is_noop)
+	#[must_use]
+	pub const fn with_empty_text_is_noop(mut self, enabled: bool) -> Self {
+		self.empty_text_is_noop = enabled;
+		self
+	}
+
+	/// Set the separator between consecutive text content,
+	/// see [`XmlWriter::set_text_separator`](crate::XmlWriter::set_text_separator)
+	#[must_use]
+	pub const fn with_text_separator(mut self, separator: &'static str) -> Self {
+		self.text_separator = separator;
+		self
+	}
+
+	/// Enable/disable normalization of line endings,
+	/// see [`XmlWriter::set_normalize_newlines`](crate::XmlWriter::set_normalize_newlines)
+	#[must_use]
+	pub const fn with_normalize_newlines(mut self, enabled: bool) -> Self {
+		self.normalize_newlines = enabled;
+		self
+	}
+
+	/// Enable/disable validation of element and attribute names,
+	/// see [`XmlWriter::set_validate_names`](crate::XmlWriter::set_validate_names)
+	#[must_use]
+	pub const fn with_validate_names(mut self, enabled: bool) -> Self {
+		self.validate_names = enabled;
+		self
+	}
+
+	/// Enable/disable rejection of names reserved by the XML specification,
+	/// see [`XmlWriter::set_reject_reserved_names`](crate::XmlWriter::set_reject_reserved_names)
+	#[must_use]
+	pub const fn with_reject_reserved_names(mut self, enabled: bool) -> Self {
+		self.reject_reserved_names = enabled;
+		self
+	}
+
+	/// Enable/disable checking of used namespace prefixes against their declarations,
+	/// see [`XmlWriter::set_track_namespaces`](crate::XmlWriter::set_track_namespaces)
+	#[must_use]
+	pub const fn with_track_namespaces(mut self, enabled: bool) -> Self {
+		self.track_namespaces = enabled;
+		self
+	}
+
+	/// Enable/disable writing elements without children as `<x></x>` instead of `<x/>`
+	#[must_use]
+	pub const fn with_expand_empty_elements(mut self, enabled: bool) -> Self {
+		self.expand_empty = enabled;
+		self
+	}
+
+	/// Enable/disable sorting of attributes,
+	/// see [`XmlWriter::set_sort_attributes`](crate::XmlWriter::set_sort_attributes)
+	#[must_use]
+	pub const fn with_sort_attributes(mut self, enabled: bool) -> Self {
+		self.sort_attributes = enabled;
+		self
+	}
+
+	/// Set the order of namespace declarations relative to the other attributes,
+	/// see [`XmlWriter::set_ns_decl_order`](crate::XmlWriter::set_ns_decl_order)
+	#[must_use]
+	pub const fn with_ns_decl_order(mut self, order: NsOrder) -> Self {
+		self.ns_order = order;
+		self
+	}
+
+	/// Set the handling of CDATA content containing `]]>`, default is [`CdataPolicy::Split`]
+	#[must_use]
+	pub const fn with_cdata_unsafe_policy(mut self, policy: CdataPolicy) -> Self {
+		self.cdata_policy = policy;
+		self
+	}
+
+	/// Set or remove the attribute value sanitizer,
+	/// see [`XmlWriter::set_attr_sanitizer`](crate::XmlWriter::set_attr_sanitizer)
+	#[must_use]
+	pub const fn with_attr_sanitizer(mut self, sanitizer: Option<AttrSanitizer>) -> Self {
+		self.attr_sanitizer = sanitizer;
+		self
+	}
+
+	/// Set or remove the write observer,
+	/// see [`XmlWriter::set_write_observer`](crate::XmlWriter::set_write_observer)
+	#[must_use]
+	pub const fn with_write_observer(mut self, observer: Option<WriteObserver>) -> Self {
+		self.write_observer = observer;
+		self
+	}
+
+	/// Set or remove the close observer,
+	/// see [`XmlWriter::set_close_observer`](crate::XmlWriter::set_close_observer)
+	#[must_use]
+	pub const fn with_close_observer(mut self, observer: Option<CloseObserver>) -> Self {
+		self.close_observer = observer;
+		self
+	}
+
+	/// Set the maximum length of attribute values in bytes, default is unlimited,
+	/// see [`XmlWriter::set_max_attr_value_len`](crate::XmlWriter::set_max_attr_value_len)
+	#[must_use]
+	pub const fn with_max_attr_value_len(mut self, max: usize) -> Self {
+		self.max_attr_value_len = max;
+		self
+	}
+
+	/// Set the maximum size of the output in bytes, default is unlimited,
+	/// see [`XmlWriter::set_max_output_bytes`](crate::XmlWriter::set_max_output_bytes)
+	#[must_use]
+	pub const fn with_max_output_bytes(mut self, max: usize) -> Self {
+		self.max_output_bytes = max;
+		self
+	}
+
+	/// Set the bytes reserved to close open elements on truncation, default is `0`,
+	/// see [`XmlWriter::set_truncation_headroom`](crate::XmlWriter::set_truncation_headroom)
+	#[must_use]
+	pub const fn with_truncation_headroom(mut self, headroom: usize) -> Self {
+		self.truncation_headroom = headroom;
+		self
+	}
+
+	/// Set the size of the internal output buffer,
+	/// see [`XmlWriter::set_buffer_size`](crate::XmlWriter::set_buffer_size)
+	#[must_use]
+	pub const fn with_buffer_size(mut self, size: usize) -> Self {
+		self.buffer_size = size;
+		self
+	}
+
+	/// Enable/disable document mode,
+	/// see [`XmlWriter::set_document_mode`](crate::XmlWriter::set_document_mode)
+	#[must_use]
+	pub const fn with_document_mode(mut self, enabled: bool) -> Self {
+		self.document = enabled;
+		self
+	}
+
+	/// Enable/disable checking the structure of the output when finished,
+	/// see [`XmlWriter::set_validate_on_finish`](crate::XmlWriter::set_validate_on_finish)
+	#[must_use]
+	pub const fn with_validate_on_finish(mut self, enabled: bool) -> Self {
+		self.validate_on_finish = enabled;
+		self
+	}
+}
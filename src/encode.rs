@@ -0,0 +1,62 @@
+// Copyright © 2026 Stephan Kunz
+//! Encoding of binary data as text content,
+//! see [`XmlWriter::elem_base64`](crate::XmlWriter::elem_base64) and
+//! [`XmlWriter::elem_hex`](crate::XmlWriter::elem_hex).
+//!
+//! The encoded text is handed out in chunks, so no allocation is needed.
+
+use crate::error::Error;
+
+/// An encoder handing the encoded text of its input in chunks to a writer
+pub type Encoder = fn(&[u8], &mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error>;
+
+/// The alphabet of standard base64
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The lowercase hex digits
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// Size of the chunks handed to the writer
+const CHUNK: usize = 64;
+
+/// Encode `data` as standard base64 with padding, handing the text in chunks to `write`.
+pub fn base64(data: &[u8], write: &mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+	let mut chunk = [0u8; CHUNK];
+	// 3 bytes are encoded as 4 characters
+	for input in data.chunks(CHUNK / 4 * 3) {
+		let mut len = 0;
+		for group in input.chunks(3) {
+			let b0 = group[0];
+			let b1 = group.get(1).copied().unwrap_or_default();
+			let b2 = group.get(2).copied().unwrap_or_default();
+			chunk[len] = BASE64[usize::from(b0 >> 2)];
+			chunk[len + 1] = BASE64[usize::from(((b0 & 0x03) << 4) | (b1 >> 4))];
+			chunk[len + 2] = if group.len() > 1 {
+				BASE64[usize::from(((b1 & 0x0f) << 2) | (b2 >> 6))]
+			} else {
+				b'='
+			};
+			chunk[len + 3] = if group.len() > 2 {
+				BASE64[usize::from(b2 & 0x3f)]
+			} else {
+				b'='
+			};
+			len += 4;
+		}
+		write(&chunk[..len])?;
+	}
+	Ok(())
+}
+
+/// Encode `data` as lowercase hex, handing the text in chunks to `write`.
+pub fn hex(data: &[u8], write: &mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+	let mut chunk = [0u8; CHUNK];
+	for input in data.chunks(CHUNK / 2) {
+		for (index, byte) in input.iter().enumerate() {
+			chunk[2 * index] = HEX[usize::from(byte >> 4)];
+			chunk[2 * index + 1] = HEX[usize::from(byte & 0x0f)];
+		}
+		write(&chunk[..2 * input.len()])?;
+	}
+	Ok(())
+}
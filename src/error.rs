@@ -1,15 +1,39 @@
 // Copyright © 2025 Stephan Kunz
 //! Woxml's errors.
 
+use alloc::string::String;
 use thiserror::Error;
 
+/// Result type used throughout woxml, defaulting to woxml's [`enum@Error`].
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
 /// Things that may go wrong during creation of the XML.
-#[derive(Error, Debug)]
+///
+/// Errors can be compared, so payloads of variants have to implement [`Eq`].
+#[derive(Error, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Error {
 	/// Closing an elemtent without having one opened.
 	#[error("attempted to close 'elem', when none was open")]
 	CloseElement,
+	/// A namespace prefix is used without a declaration in scope, or a namespace URI without a registered prefix.
+	#[error("namespace '{namespace}' is not declared")]
+	UndeclaredNamespace {
+		/// the undeclared prefix or namespace URI
+		namespace: String,
+	},
+	/// Elements are still open when the output is taken.
+	#[error("number of elements still open: {count}")]
+	UnclosedElements {
+		/// the number of open elements
+		count: usize,
+	},
+	/// The internal element and namespace stacks are out of sync.
+	#[error("element and namespace stacks are out of sync")]
+	StackDesync,
+	/// Closing an element with a token of another element.
+	#[error("attempted to close 'elem' with the token of another 'elem'")]
+	MismatchedClose,
 	/// Closing a namespace without having one opened.
 	#[error("attempted to close namespaced 'elem' without corresponding open namespace")]
 	CloseNamespace,
@@ -19,10 +43,121 @@ pub enum Error {
 	/// Attempt to write without having an element opened.
 	#[error("attempted to write 'attr' to 'elem', when no 'elem' was opened")]
 	WriteWithoutElement,
+	/// A namespace prefix is declared twice within one element, the default namespace has an empty prefix.
+	#[error("namespace prefix '{prefix}' declared twice")]
+	DuplicateNamespacePrefix {
+		/// The duplicated prefix
+		prefix: String,
+	},
+	/// A reserved namespace prefix is declared, `xmlns` at all or `xml` with another than its standard URI.
+	#[error("reserved namespace prefix '{prefix}' declared")]
+	ReservedPrefix {
+		/// The reserved prefix
+		prefix: String,
+	},
+	/// A name starting with `xml` is reserved by the XML specification,
+	/// see [`XmlWriter::set_reject_reserved_names`](crate::XmlWriter::set_reject_reserved_names).
+	#[error("reserved name '{name}' used")]
+	ReservedName {
+		/// The reserved name
+		name: String,
+	},
+	/// A name does not match the XML naming rules.
+	#[error("invalid XML name")]
+	InvalidName,
+	/// An attribute value has been rejected by the sanitizer.
+	#[error("attribute value rejected by sanitizer")]
+	RejectedAttrValue,
+	/// A document has been finished without a root element.
+	#[error("attempted to finish a document without root element")]
+	EmptyDocument,
+	/// A document has more than one root element.
+	#[error("attempted to finish a document with more than one root element")]
+	MultipleRoots,
+	/// The structure of the output is malformed.
+	#[error("malformed output: {reason}")]
+	MalformedOutput {
+		/// The reason why the output is malformed
+		reason: &'static str,
+	},
+	/// The structure of the input is malformed, see [`reformat`](crate::reformat).
+	#[error("malformed input: {reason}")]
+	MalformedInput {
+		/// The reason why the input is malformed
+		reason: &'static str,
+	},
+	/// A code point is not a legal XML character.
+	#[error("invalid XML character")]
+	InvalidCharacter,
+	/// An attribute value exceeds the maximum length.
+	#[error("attribute value of {len} bytes exceeds the maximum of {max} bytes")]
+	AttrValueTooLong {
+		/// The length of the value
+		len: usize,
+		/// The maximum length
+		max: usize,
+	},
+	/// A comment contains characters which are not allowed.
+	#[error("invalid comment")]
+	InvalidComment,
+	/// A CDATA section contains the end marker `]]>`.
+	#[error("CDATA contains the end marker ']]>'")]
+	InvalidCdata,
+	/// A processing instruction contains characters which are not allowed.
+	#[error("invalid processing instruction")]
+	InvalidProcessingInstruction,
+	/// The XML declaration has to be the first output of a document.
+	#[error("attempted to write the XML declaration after content")]
+	ContentAlreadyWritten,
+	/// The buffer does not support truncation.
+	#[error("buffer does not support truncation")]
+	TruncateUnsupported,
+	/// A checkpoint does not match the current output.
+	#[error("checkpoint does not belong to the current output")]
+	InvalidCheckpoint,
+	/// The most recent write did not close an element which could be removed.
+	#[error("no element has been closed by the most recent write")]
+	NoLastElement,
+	/// An element decorator wrote other content than attributes.
+	#[error("element decorator wrote other content than attributes")]
+	DecoratorWroteContent,
+	/// A fixed size buffer has no capacity left.
+	#[error("buffer is full")]
+	BufferFull,
+	/// Formatting of content failed.
+	#[error("failed to format content")]
+	Fmt,
+	/// The lock of a shared buffer is poisoned.
+	#[error("lock of shared buffer is poisoned")]
+	LockPoisoned,
 	/// Writing the buffer failed.
 	#[error("failed to write buffer")]
 	WriteAllEof,
+	/// An I/O operation failed, e.g. of a sink or in code mixing I/O and writing XML.
+	/// Only the kind of the [`std::io::Error`] is kept, so that errors can still be compared.
+	#[cfg(feature = "std")]
+	#[error("I/O error: {0}")]
+	Io(std::io::ErrorKind),
 	/// Conversion of buffer into String failed.
 	#[error("failed parsing buffer as UTF8")]
 	ParsingUtf8,
 }
+
+impl From<core::fmt::Error> for Error {
+	fn from(_: core::fmt::Error) -> Self {
+		Self::Fmt
+	}
+}
+
+impl From<core::convert::Infallible> for Error {
+	fn from(infallible: core::convert::Infallible) -> Self {
+		match infallible {}
+	}
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Self {
+		Self::Io(error.kind())
+	}
+}
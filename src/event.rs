@@ -0,0 +1,48 @@
+// Copyright © 2026 Stephan Kunz
+//! Recording of the high-level operations performed by the [`XmlWriter`](crate::XmlWriter).
+
+use alloc::{borrow::Cow, string::String};
+
+/// A high-level operation performed by the [`XmlWriter`](crate::XmlWriter),
+/// see [`XmlWriter::set_record_events`](crate::XmlWriter::set_record_events).
+/// All content is recorded unescaped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event<'a> {
+	/// An element has been opened.
+	StartElem {
+		/// The namespace prefix of the element.
+		namespace: Option<&'a str>,
+		/// The name of the element.
+		name: Cow<'a, str>,
+	},
+	/// An element has been closed.
+	EndElem {
+		/// The namespace prefix of the element.
+		namespace: Option<&'a str>,
+		/// The name of the element.
+		name: Cow<'a, str>,
+	},
+	/// An attribute has been written into the currently open element.
+	Attr {
+		/// The name of the attribute including a possible prefix.
+		name: String,
+		/// The value of the attribute.
+		value: String,
+	},
+	/// Text content has been written.
+	Text(String),
+	/// A comment has been written.
+	Comment(String),
+	/// A CDATA section has been written.
+	Cdata(String),
+	/// An entity reference has been written as text content, with the name of the entity.
+	EntityRef(String),
+	/// A processing instruction has been written.
+	Pi {
+		/// The target of the processing instruction.
+		target: String,
+		/// The data of the processing instruction, pseudo-attributes are recorded like `key="value"`.
+		data: String,
+	},
+}
@@ -5,12 +5,34 @@
 
 #[doc(hidden)]
 extern crate alloc;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+extern crate std;
 
+#[cfg(feature = "tokio")]
+mod buffered;
+mod config;
+mod encode;
 mod error;
+mod event;
+mod name;
+pub mod prelude;
+mod reformat;
+mod scanner;
+mod sink;
 mod woxml;
 mod write;
 
 // flatten
-pub use error::Error;
-pub use woxml::XmlWriter;
+#[cfg(feature = "tokio")]
+pub use buffered::AsyncBufferedWriter;
+pub use config::{
+	ApostropheStyle, AttrSanitizer, CdataPolicy, CloseObserver, Config, IndentUnit, NsOrder, Quote, WriteObserver,
+};
+pub use error::{Error, Result};
+pub use event::Event;
+pub use name::{is_name, is_ncname};
+pub use reformat::reformat;
+pub use sink::{Discard, EventSink};
+pub use woxml::{Checkpoint, ElementDecorator, OpenElem, XmlWriter};
 pub use write::Write;
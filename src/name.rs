@@ -0,0 +1,129 @@
+// Copyright © 2026 Stephan Kunz
+//! Validation of XML names according to the
+//! [Name](https://www.w3.org/TR/xml/#NT-Name) and
+//! [NCName](https://www.w3.org/TR/xml-names/#NT-NCName) productions
+//! and of characters according to the [Char](https://www.w3.org/TR/xml/#NT-Char) production.
+
+/// Decode the character starting at byte `index` of a valid UTF-8 sequence.
+/// Returns the code point and its encoded length.
+const fn decode(bytes: &[u8], index: usize) -> (u32, usize) {
+	let first = bytes[index] as u32;
+	if first < 0x80 {
+		(first, 1)
+	} else if first < 0xE0 {
+		(((first & 0x1F) << 6) | (bytes[index + 1] as u32 & 0x3F), 2)
+	} else if first < 0xF0 {
+		(
+			((first & 0x0F) << 12) | ((bytes[index + 1] as u32 & 0x3F) << 6) | (bytes[index + 2] as u32 & 0x3F),
+			3,
+		)
+	} else {
+		(
+			((first & 0x07) << 18)
+				| ((bytes[index + 1] as u32 & 0x3F) << 12)
+				| ((bytes[index + 2] as u32 & 0x3F) << 6)
+				| (bytes[index + 3] as u32 & 0x3F),
+			4,
+		)
+	}
+}
+
+/// `NameStartChar` without the colon.
+const fn is_start_char(c: u32) -> bool {
+	matches!(c,
+		0x41..=0x5A // A-Z
+		| 0x5F // _
+		| 0x61..=0x7A // a-z
+		| 0xC0..=0xD6
+		| 0xD8..=0xF6
+		| 0xF8..=0x2FF
+		| 0x370..=0x37D
+		| 0x37F..=0x1FFF
+		| 0x200C..=0x200D
+		| 0x2070..=0x218F
+		| 0x2C00..=0x2FEF
+		| 0x3001..=0xD7FF
+		| 0xF900..=0xFDCF
+		| 0xFDF0..=0xFFFD
+		| 0x10000..=0xEFFFF
+	)
+}
+
+/// `NameChar` without the colon.
+const fn is_char(c: u32) -> bool {
+	is_start_char(c)
+		|| matches!(c,
+			0x2D // -
+			| 0x2E // .
+			| 0x30..=0x39 // 0-9
+			| 0xB7
+			| 0x300..=0x36F
+			| 0x203F..=0x2040
+		)
+}
+
+/// Check `name` against the production, allowing colons if `colon` is `true`.
+const fn check(name: &str, colon: bool) -> bool {
+	let bytes = name.as_bytes();
+	if bytes.is_empty() {
+		return false;
+	}
+	let mut index = 0;
+	while index < bytes.len() {
+		let (c, len) = decode(bytes, index);
+		let valid = if colon && c == 0x3A {
+			true
+		} else if index == 0 {
+			is_start_char(c)
+		} else {
+			is_char(c)
+		};
+		if !valid {
+			return false;
+		}
+		index += len;
+	}
+	true
+}
+
+/// Returns `true` if `name` matches the XML `Name` production.
+#[must_use]
+pub const fn is_name(name: &str) -> bool {
+	check(name, true)
+}
+
+/// Returns `true` if `name` matches the `NCName` production,
+/// which is a `Name` without any colon.
+#[must_use]
+pub const fn is_ncname(name: &str) -> bool {
+	check(name, false)
+}
+
+/// Returns `true` if the code point `c` matches the XML `Char` production.
+pub const fn is_xml_char(c: u32) -> bool {
+	matches!(c, 0x9 | 0xA | 0xD | 0x20..=0xD7FF | 0xE000..=0xFFFD | 0x10000..=0x10_FFFF)
+}
+
+/// Validates a string literal against the XML `Name` production at compile time.
+///
+/// Evaluates to the literal as `&'static str`, e.g. for [`XmlWriter::begin_elem`](crate::XmlWriter::begin_elem).
+/// An invalid name fails the compilation.
+/// ```
+/// use woxml::{XmlWriter, elem_name};
+///
+/// let mut xml = XmlWriter::compact_mode(Vec::new());
+/// xml.begin_elem(elem_name!("foo-bar"))?;
+/// xml.end_elem()?;
+/// # Ok::<(), woxml::Error>(())
+/// ```
+/// ```compile_fail
+/// let name = woxml::elem_name!("1bad");
+/// ```
+#[macro_export]
+macro_rules! elem_name {
+	($name:literal) => {{
+		const NAME: &str = $name;
+		const _: () = assert!($crate::is_name(NAME), "invalid XML name");
+		NAME
+	}};
+}
@@ -0,0 +1,24 @@
+// Copyright © 2026 Stephan Kunz
+//! The woxml prelude.
+//!
+//! Brings the commonly used items into scope with a single glob import:
+//! ```
+//! use woxml::prelude::*;
+//!
+//! let mut xml = XmlWriter::compact_mode(Vec::new());
+//! xml.begin_elem("root").unwrap();
+//! xml.end_elem().unwrap();
+//! ```
+
+#[cfg(feature = "tokio")]
+pub use crate::buffered::AsyncBufferedWriter;
+pub use crate::{
+	config::{
+		ApostropheStyle, AttrSanitizer, CdataPolicy, CloseObserver, Config, IndentUnit, NsOrder, Quote, WriteObserver,
+	},
+	error::{Error, Result},
+	event::Event,
+	sink::{Discard, EventSink},
+	woxml::{Checkpoint, ElementDecorator, OpenElem, XmlWriter},
+	write::Write,
+};
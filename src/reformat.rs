@@ -0,0 +1,240 @@
+// Copyright © 2026 Stephan Kunz
+//! Reformatting existing XML through an [`XmlWriter`], see [`reformat`].
+
+use alloc::string::String;
+
+use crate::{
+	error::Error,
+	scanner::{self, find, tag_end},
+	woxml::XmlWriter,
+};
+
+/// Reformat the well-formed XML `input` in `pretty` or compact mode,
+/// e.g. to normalize XML from elsewhere to the formatting of generated files.
+/// ```
+/// let pretty = woxml::reformat("<log><entry n=\"1\">text</entry></log>", true)?;
+/// assert_eq!(pretty, "<log>\n  <entry n=\"1\">text</entry>\n</log>");
+/// # Ok::<(), woxml::Error>(())
+/// ```
+/// The input is tokenized just enough to drive the writer, names and references are not validated.
+/// Text consisting of whitespace only is dropped, except within elements holding text,
+/// which are written on one line like [`XmlWriter::inline_elem`], so that mixed content is kept.
+/// Comments are trimmed, and the XML declaration is rewritten with its encoding only.
+/// Reformatting the output again does not change it.
+/// Attribute values and character references are kept as written, empty elements are self-closed.
+/// # Errors
+/// - [`Error::MalformedInput`] if the structure of `input` is malformed or a reference is unterminated
+/// - if the writer rejects a token, e.g. a processing instruction containing `?>`
+pub fn reformat(input: &str, pretty: bool) -> Result<String, Error> {
+	let bytes = input.as_bytes();
+	scanner::check(bytes, false).map_err(|reason| Error::MalformedInput { reason })?;
+	let mut xml = if pretty {
+		XmlWriter::pretty_mode(alloc::vec::Vec::new())
+	} else {
+		XmlWriter::compact_mode(alloc::vec::Vec::new())
+	};
+	// the depth of open elems and the one of the outermost elem holding text
+	let mut depth = 0_usize;
+	let mut inline = None;
+	let mut index = 0;
+	while index < bytes.len() {
+		let rest = &bytes[index..];
+		// the structure has been checked, so all constructs are terminated
+		let end = if rest.starts_with(b"<?") {
+			let end = find(bytes, index + 2, b"?>").unwrap_or(bytes.len());
+			pi(&mut xml, &input[index + 2..end - 2])?;
+			end
+		} else if rest.starts_with(b"<!--") {
+			let end = find(bytes, index + 4, b"-->").unwrap_or(bytes.len());
+			xml.comment_raw(input[index + 4..end - 3].trim())?;
+			end
+		} else if rest.starts_with(b"<![CDATA[") {
+			let end = find(bytes, index + 9, b"]]>").unwrap_or(bytes.len());
+			xml.cdata(&input[index + 9..end - 3])?;
+			end
+		} else if rest.starts_with(b"<!") {
+			let end = tag_end(bytes, index + 2).unwrap_or(bytes.len());
+			let end = if bytes[index..end].contains(&b'[') {
+				find(bytes, index + 2, b"]>").unwrap_or(bytes.len())
+			} else {
+				end
+			};
+			xml.doctype_raw(&input[index..end])?;
+			end
+		} else if rest.starts_with(b"</") {
+			xml.end_elem()?;
+			if inline == Some(depth) {
+				inline = None;
+			}
+			depth = depth.saturating_sub(1);
+			tag_end(bytes, index + 2).unwrap_or(bytes.len())
+		} else if rest.starts_with(b"<") {
+			let end = tag_end(bytes, index + 1).unwrap_or(bytes.len());
+			let tag = &input[index + 1..end - 1];
+			if !tag.ends_with('/') {
+				depth += 1;
+				if inline.is_none() && holds_text(bytes, end) {
+					inline = Some(depth);
+				}
+			}
+			start_tag(&mut xml, tag, inline == Some(depth))?;
+			end
+		} else {
+			let end = rest
+				.iter()
+				.position(|byte| *byte == b'<')
+				.map_or(bytes.len(), |len| index + len);
+			let text = &input[index..end];
+			if inline.is_some() || !text.trim_ascii().is_empty() {
+				write_text(&mut xml, text)?;
+			}
+			end
+		};
+		index = end;
+	}
+	String::try_from(xml)
+}
+
+/// Write the processing instruction or XML declaration with the content `content`
+/// # Errors
+/// - if the writer rejects the processing instruction
+fn pi(xml: &mut XmlWriter<'_, alloc::vec::Vec<u8>>, content: &str) -> Result<(), Error> {
+	let (target, data) = content
+		.split_once(|char: char| char.is_ascii_whitespace())
+		.unwrap_or((content, ""));
+	if target != "xml" {
+		return xml.pi(target, data.trim());
+	}
+	let encoding = data
+		.split_once("encoding")
+		.and_then(|(_, rest)| {
+			let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+			let quote = rest
+				.chars()
+				.next()
+				.filter(|quote| matches!(quote, '"' | '\''))?;
+			rest[1..].split(quote).next()
+		})
+		.unwrap_or("UTF-8");
+	xml.dtd(encoding)
+}
+
+/// Whether the elem with its content starting at `index` holds text besides whitespace,
+/// not counting the content of its child elems
+fn holds_text(bytes: &[u8], mut index: usize) -> bool {
+	let mut depth = 0_usize;
+	while index < bytes.len() {
+		let rest = &bytes[index..];
+		let end = if rest.starts_with(b"<?") {
+			find(bytes, index + 2, b"?>")
+		} else if rest.starts_with(b"<!--") {
+			find(bytes, index + 4, b"-->")
+		} else if rest.starts_with(b"<![CDATA[") {
+			find(bytes, index + 9, b"]]>")
+		} else if rest.starts_with(b"</") {
+			if depth == 0 {
+				return false;
+			}
+			depth -= 1;
+			tag_end(bytes, index + 2)
+		} else if rest.starts_with(b"<") {
+			let end = tag_end(bytes, index + 1);
+			if end.is_some_and(|end| bytes[end - 2] != b'/') {
+				depth += 1;
+			}
+			end
+		} else {
+			let len = rest
+				.iter()
+				.position(|byte| *byte == b'<')
+				.unwrap_or(rest.len());
+			if depth == 0 && !rest[..len].trim_ascii().is_empty() {
+				return true;
+			}
+			Some(index + len)
+		};
+		index = end.unwrap_or(bytes.len());
+	}
+	false
+}
+
+/// Write the start tag with the content `tag` between `<` and `>`, closing it if it is empty,
+/// as an inline elem if `inline`
+/// # Errors
+/// - if the writer rejects the name or an attribute
+fn start_tag<'a>(xml: &mut XmlWriter<'a, alloc::vec::Vec<u8>>, tag: &'a str, inline: bool) -> Result<(), Error> {
+	let (mut rest, empty) = tag
+		.strip_suffix('/')
+		.map_or((tag, false), |tag| (tag, true));
+	let name = core::str::from_utf8(scanner::name(rest.as_bytes())).map_err(|_| Error::ParsingUtf8)?;
+	if inline {
+		xml.inline_elem(name)?;
+	} else {
+		xml.begin_elem(name)?;
+	}
+	rest = rest[name.len()..].trim_start();
+	while let Some((attr, value)) = rest.split_once('=') {
+		let value = value.trim_start();
+		let Some(quote) = value
+			.chars()
+			.next()
+			.filter(|quote| matches!(quote, '"' | '\''))
+		else {
+			break;
+		};
+		let Some((value, next)) = value[1..].split_once(quote) else {
+			break;
+		};
+		if value.contains('"') {
+			xml.attr_raw(attr.trim(), &value.replace('"', "&quot;"))?;
+		} else {
+			xml.attr_raw(attr.trim(), value)?;
+		}
+		rest = next.trim_start();
+	}
+	if empty {
+		xml.end_elem()?;
+	}
+	Ok(())
+}
+
+/// Write the text `text`, decoding the predefined entities and keeping other references
+/// # Errors
+/// - [`Error::MalformedInput`] if a reference is unterminated
+/// - if the writer rejects a reference
+fn write_text(xml: &mut XmlWriter<'_, alloc::vec::Vec<u8>>, text: &str) -> Result<(), Error> {
+	let mut decoded = String::new();
+	let mut rest = text;
+	while let Some((before, after)) = rest.split_once('&') {
+		decoded.push_str(before);
+		let (reference, next) = after
+			.split_once(';')
+			.ok_or(Error::MalformedInput {
+				reason: "unterminated reference",
+			})?;
+		match reference {
+			"lt" => decoded.push('<'),
+			"gt" => decoded.push('>'),
+			"amp" => decoded.push('&'),
+			"quot" => decoded.push('"'),
+			"apos" => decoded.push('\''),
+			_ => {
+				xml.text(&decoded)?;
+				decoded.clear();
+				let invalid = || Error::MalformedInput {
+					reason: "invalid character reference",
+				};
+				if let Some(hex) = reference.strip_prefix("#x") {
+					xml.char_ref_hex(u32::from_str_radix(hex, 16).map_err(|_| invalid())?)?;
+				} else if let Some(decimal) = reference.strip_prefix('#') {
+					xml.char_ref(decimal.parse().map_err(|_| invalid())?)?;
+				} else {
+					xml.raw_entity(reference)?;
+				}
+			}
+		}
+		rest = next;
+	}
+	decoded.push_str(rest);
+	xml.text(&decoded)
+}
@@ -0,0 +1,255 @@
+// Copyright © 2026 Stephan Kunz
+//! A lightweight well-formedness check of generated output,
+//! see [`XmlWriter::set_validate_on_finish`](crate::XmlWriter::set_validate_on_finish).
+//!
+//! Only the structure is checked: balanced tags, and in document mode a single root element
+//! without content outside of it. Names, attributes and references are not validated.
+//!
+//! The same light parsing is used for a verbose dump of the output,
+//! see [`XmlWriter::debug_dump`](crate::XmlWriter::debug_dump),
+//! to close truncated output, see [`XmlWriter::set_truncation_headroom`](crate::XmlWriter::set_truncation_headroom),
+//! and to tokenize existing XML, see [`reformat`](crate::reformat).
+
+use alloc::{string::String, vec::Vec};
+
+/// Find `pattern` in `bytes` starting at `from`, returns the index after the pattern.
+pub fn find(bytes: &[u8], from: usize, pattern: &[u8]) -> Option<usize> {
+	bytes
+		.get(from..)?
+		.windows(pattern.len())
+		.position(|window| window == pattern)
+		.map(|index| from + index + pattern.len())
+}
+
+/// Find the end of a tag starting at `from`, skipping quoted attribute values.
+/// Returns the index after the closing `>`.
+pub fn tag_end(bytes: &[u8], from: usize) -> Option<usize> {
+	let mut quote = None;
+	for (index, byte) in bytes.iter().enumerate().skip(from) {
+		match (quote, byte) {
+			(None, b'"' | b'\'') => quote = Some(*byte),
+			(None, b'>') => return Some(index + 1),
+			(Some(open), _) if open == *byte => quote = None,
+			_ => {}
+		}
+	}
+	None
+}
+
+/// The name at the start of `bytes`, ending at whitespace, `/` or `>`.
+pub fn name(bytes: &[u8]) -> &[u8] {
+	let len = bytes
+		.iter()
+		.position(|byte| byte.is_ascii_whitespace() || matches!(byte, b'/' | b'>'))
+		.unwrap_or(bytes.len());
+	&bytes[..len]
+}
+
+/// Check the structure of `bytes`, returns the reason if it is malformed.
+/// If `document` is `true`, a single root element is required.
+pub fn check(bytes: &[u8], document: bool) -> Result<(), &'static str> {
+	let mut stack: Vec<&[u8]> = Vec::new();
+	let mut roots = 0;
+	let mut index = 0;
+	while index < bytes.len() {
+		let rest = &bytes[index..];
+		if rest.starts_with(b"<?") {
+			index = find(bytes, index + 2, b"?>").ok_or("unterminated processing instruction")?;
+		} else if rest.starts_with(b"<!--") {
+			index = find(bytes, index + 4, b"-->").ok_or("unterminated comment")?;
+		} else if rest.starts_with(b"<![CDATA[") {
+			if document && stack.is_empty() {
+				return Err("content outside of the root element");
+			}
+			index = find(bytes, index + 9, b"]]>").ok_or("unterminated CDATA section")?;
+		} else if rest.starts_with(b"<!") {
+			// a DTD with a possible internal subset
+			let end = tag_end(bytes, index + 2).ok_or("unterminated declaration")?;
+			index = if bytes[index..end].contains(&b'[') {
+				find(bytes, index + 2, b"]>").ok_or("unterminated declaration")?
+			} else {
+				end
+			};
+		} else if rest.starts_with(b"</") {
+			let end = tag_end(bytes, index + 2).ok_or("unterminated end tag")?;
+			let open = stack.pop().ok_or("end tag without start tag")?;
+			if name(&bytes[index + 2..end]) != open {
+				return Err("mismatched end tag");
+			}
+			if stack.is_empty() {
+				roots += 1;
+			}
+			index = end;
+		} else if rest.starts_with(b"<") {
+			let end = tag_end(bytes, index + 1).ok_or("unterminated start tag")?;
+			if document && stack.is_empty() && roots > 0 {
+				return Err("multiple root elements");
+			}
+			let tag = name(&bytes[index + 1..end]);
+			if tag.is_empty() {
+				return Err("start tag without name");
+			}
+			if bytes[end - 2] == b'/' {
+				if stack.is_empty() {
+					roots += 1;
+				}
+			} else {
+				stack.push(tag);
+			}
+			index = end;
+		} else {
+			let end = rest
+				.iter()
+				.position(|byte| *byte == b'<')
+				.map_or(bytes.len(), |len| index + len);
+			if document && stack.is_empty() && !bytes[index..end].trim_ascii().is_empty() {
+				return Err("content outside of the root element");
+			}
+			index = end;
+		}
+	}
+	if !stack.is_empty() {
+		return Err("unclosed element");
+	}
+	Ok(())
+}
+
+/// Find the end of the last complete construct in `bytes`, so that the output can be cut there.
+/// Returns that index and the names of the elements open at it, outermost first.
+pub fn last_complete(bytes: &[u8]) -> (usize, Vec<&[u8]>) {
+	let mut stack: Vec<&[u8]> = Vec::new();
+	let mut index = 0;
+	while index < bytes.len() {
+		let rest = &bytes[index..];
+		let end = if rest.starts_with(b"<?") {
+			find(bytes, index + 2, b"?>")
+		} else if rest.starts_with(b"<!--") {
+			find(bytes, index + 4, b"-->")
+		} else if rest.starts_with(b"<![CDATA[") {
+			find(bytes, index + 9, b"]]>")
+		} else if rest.starts_with(b"<!") {
+			tag_end(bytes, index + 2).and_then(|end| {
+				if bytes[index..end].contains(&b'[') {
+					find(bytes, index + 2, b"]>")
+				} else {
+					Some(end)
+				}
+			})
+		} else if rest.starts_with(b"</") {
+			tag_end(bytes, index + 2).inspect(|_| {
+				stack.pop();
+			})
+		} else if rest.starts_with(b"<") {
+			tag_end(bytes, index + 1).inspect(|end| {
+				if bytes[end - 2] != b'/' {
+					stack.push(name(&bytes[index + 1..*end]));
+				}
+			})
+		} else {
+			// text is written in whole pieces, so it may be cut anywhere
+			Some(
+				rest.iter()
+					.position(|byte| *byte == b'<')
+					.map_or(bytes.len(), |len| index + len),
+			)
+		};
+		match end {
+			Some(end) => index = end,
+			None => break,
+		}
+	}
+	(index, stack)
+}
+
+/// Start a new line in `out` indented by `width` spaces, unless it is the first line.
+fn new_line(out: &mut Vec<u8>, width: usize) {
+	if !out.is_empty() {
+		out.push(b'\n');
+	}
+	out.resize(out.len() + width, b' ');
+}
+
+/// Write the start tag `tag` with each attribute on its own line indented by `width` spaces.
+fn dump_start_tag(out: &mut Vec<u8>, tag: &[u8], width: usize) {
+	let mut index = 1 + name(&tag[1..]).len();
+	out.extend_from_slice(&tag[..index]);
+	loop {
+		while tag
+			.get(index)
+			.is_some_and(u8::is_ascii_whitespace)
+		{
+			index += 1;
+		}
+		let start = index;
+		let end = tag
+			.iter()
+			.skip(start)
+			.position(|byte| matches!(byte, b'"' | b'\''))
+			.and_then(|offset| find(tag, start + offset + 1, &tag[start + offset..=start + offset]));
+		match end {
+			Some(end) if !matches!(tag.get(start), Some(b'/' | b'>')) => {
+				new_line(out, width);
+				out.extend_from_slice(&tag[start..end]);
+				index = end;
+			}
+			_ => break,
+		}
+	}
+	out.extend_from_slice(&tag[index..]);
+}
+
+/// Reformat `bytes` for human inspection: each tag, comment, processing instruction and text
+/// on its own line indented by its depth, each attribute on its own line below its start tag.
+/// Whitespace around text is trimmed, unterminated constructs are copied as is.
+pub fn dump(bytes: &[u8]) -> String {
+	let mut out = Vec::new();
+	let mut depth: usize = 0;
+	let mut index = 0;
+	while index < bytes.len() {
+		let rest = &bytes[index..];
+		let end = if rest.starts_with(b"<?") {
+			find(bytes, index + 2, b"?>")
+		} else if rest.starts_with(b"<!--") {
+			find(bytes, index + 4, b"-->")
+		} else if rest.starts_with(b"<![CDATA[") {
+			find(bytes, index + 9, b"]]>")
+		} else if rest.starts_with(b"<!") {
+			tag_end(bytes, index + 2).and_then(|end| {
+				if bytes[index..end].contains(&b'[') {
+					find(bytes, index + 2, b"]>")
+				} else {
+					Some(end)
+				}
+			})
+		} else if rest.starts_with(b"</") {
+			depth = depth.saturating_sub(1);
+			tag_end(bytes, index + 2)
+		} else if rest.starts_with(b"<") {
+			let end = tag_end(bytes, index + 1).unwrap_or(bytes.len());
+			new_line(&mut out, 2 * depth);
+			dump_start_tag(&mut out, &bytes[index..end], 2 * depth + 4);
+			if !bytes[..end].ends_with(b"/>") {
+				depth += 1;
+			}
+			index = end;
+			continue;
+		} else {
+			let end = rest
+				.iter()
+				.position(|byte| *byte == b'<')
+				.map_or(bytes.len(), |len| index + len);
+			let text = bytes[index..end].trim_ascii();
+			if !text.is_empty() {
+				new_line(&mut out, 2 * depth);
+				out.extend_from_slice(text);
+			}
+			index = end;
+			continue;
+		};
+		let end = end.unwrap_or(bytes.len());
+		new_line(&mut out, 2 * depth);
+		out.extend_from_slice(&bytes[index..end]);
+		index = end;
+	}
+	String::from_utf8_lossy(&out).into_owned()
+}
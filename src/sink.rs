@@ -0,0 +1,67 @@
+// Copyright © 2026 Stephan Kunz
+//! Receiving the [`Event`]s of an [`XmlWriter`](crate::XmlWriter) instead of or in addition to its output,
+//! see [`XmlWriter::set_event_sink`](crate::XmlWriter::set_event_sink).
+
+use alloc::vec::Vec;
+
+use crate::{error::Error, event::Event, write::Write};
+
+/// A receiver of the [`Event`]s of an [`XmlWriter`](crate::XmlWriter),
+/// e.g. a DOM builder or another serializer reusing the state handling and checks of the writer.
+///
+/// The events are sent when the operation starts, attributes follow the [`Event::StartElem`] of their element.
+/// Events already sent are not retracted by [`XmlWriter::rollback`](crate::XmlWriter::rollback).
+pub trait EventSink<'a> {
+	/// Receive the next event.
+	/// # Errors
+	/// Any error aborts the operation of the writer and is returned by it.
+	fn event(&mut self, event: Event<'a>) -> Result<(), Error>;
+}
+
+/// [`EventSink`] implementation collecting the events in a [`Vec`].
+impl<'a> EventSink<'a> for Vec<Event<'a>> {
+	#[inline]
+	fn event(&mut self, event: Event<'a>) -> Result<(), Error> {
+		self.push(event);
+		Ok(())
+	}
+}
+
+/// [`EventSink`] implementation for a mutable reference, so that the sink can be inspected afterwards.
+impl<'a, S: EventSink<'a> + ?Sized> EventSink<'a> for &mut S {
+	#[inline]
+	fn event(&mut self, event: Event<'a>) -> Result<(), Error> {
+		(**self).event(event)
+	}
+}
+
+/// [`EventSink`] implementation for a shared [`std::sync::Arc<std::sync::Mutex<S>>`](std::sync::Mutex),
+/// e.g. to inspect collected events borrowing from the same data as the writer.
+#[cfg(feature = "std")]
+impl<'a, S: EventSink<'a>> EventSink<'a> for std::sync::Arc<std::sync::Mutex<S>> {
+	#[inline]
+	fn event(&mut self, event: Event<'a>) -> Result<(), Error> {
+		self.lock()
+			.map_err(|_| Error::LockPoisoned)?
+			.event(event)
+	}
+}
+
+/// A [`Write`] implementation discarding all output,
+/// for an [`XmlWriter`](crate::XmlWriter) sending its events to an [`EventSink`] only,
+/// see [`XmlWriter::event_mode`](crate::XmlWriter::event_mode).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Discard;
+
+/// [`Write`] implementation for [`Discard`].
+impl Write for Discard {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		Ok(buf.len())
+	}
+}
@@ -3,15 +3,22 @@
 
 //! Implementation of the `XmlWriter`.
 
-use alloc::{
-	boxed::Box,
-	string::{String, ToString},
-	vec::Vec,
-};
+use alloc::{borrow::Cow, boxed::Box, format, string::String, vec::Vec};
 
 use core::result::Result;
 
-use crate::{error::Error, write::Write};
+use crate::{
+	config::{
+		ApostropheStyle, AttrSanitizer, CdataPolicy, CloseObserver, Config, IndentUnit, NsOrder, Quote, WriteObserver,
+	},
+	encode,
+	error::Error,
+	event::Event,
+	name::{is_name, is_ncname, is_xml_char},
+	scanner,
+	sink::{Discard, EventSink},
+	write::Write,
+};
 
 /// Multiple used literal definitions
 const CLOSE: &str = ">";
@@ -19,34 +26,214 @@ const CLOSE_CLOSE: &str = "/>";
 const OPEN: &str = "<";
 const SELF_CLOSE_OPEN: &str = "</";
 const SPACE: &str = " ";
-const EQUAL_QUOTE: &str = "=\"";
-const QUOTE: &str = "\"";
+/// The namespace URI bound to the reserved `xml` prefix
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Returns `true` if declaring `prefix` for `uri` is forbidden,
+/// which is the case for `xmlns` and for `xml` with another than its standard URI.
+fn is_reserved_prefix(prefix: &str, uri: &str) -> bool {
+	prefix == "xmlns" || (prefix == "xml" && uri != XML_NAMESPACE)
+}
+
+/// Returns `true` if `name` starts with `xml` in any case, which is reserved by the XML specification,
+/// except for the known names of the `xml` prefix and namespace declarations.
+fn is_reserved_name(name: &str) -> bool {
+	name.get(..3)
+		.is_some_and(|start| start.eq_ignore_ascii_case("xml"))
+		&& !matches!(name, "xml:lang" | "xml:space" | "xml:base" | "xml:id" | "xmlns")
+		&& !name.starts_with("xmlns:")
+}
+
+/// The kinds of content to escape.
+#[derive(Clone, Copy)]
+enum Escape {
+	/// Attribute names, backslashes are escaped additionally
+	Name,
+	/// Text content
+	Text,
+	/// Attribute values
+	Value,
+	/// Attribute values with only `&`, `<` and the delimiting quote escaped
+	Minimal,
+	/// Verbatim text with only `&`, `<` and a `>` following `]]` escaped
+	Verbatim,
+}
+
+/// A callback adding attributes to each element, see [`XmlWriter::set_element_decorator`].
+pub type ElementDecorator<'a, W> = fn(&mut XmlWriter<'a, W>, &str) -> Result<(), Error>;
 
 /// The `XmlWriter` himself.
 /// Elements without children are automatically self-closing.
 /// In 'pretty' mode the writer will
 /// - indent all opening elements on a new line
 /// - put closing elements into own line
+///
+/// The writer is [`Send`] if its buffer is, it is not [`Sync`] as its event sink needs not to be.
+/// To share output across threads use a shared buffer like `Arc<Mutex<Vec<u8>>>` (feature `std`).
+#[allow(clippy::struct_excessive_bools)]
 pub struct XmlWriter<'a, Buffer: Write> {
-	/// element stack: (name, `has_children`, namespace at time of push)
-	stack: Vec<(&'a str, bool, Option<&'a str>)>,
+	/// element stack: (name, `has_children`)
+	stack: Vec<(&'a str, bool)>,
+	/// namespace stack: namespace at time of push, in sync with the element stack
+	ns_stack: Vec<Option<&'a str>>,
+	/// namespace scopes: depth of the elem opening the scope and the namespace to restore when it closes
+	ns_scopes: Vec<(usize, Option<&'a str>)>,
+	/// declared namespace prefixes: depth of the declaring elem and the prefix
+	ns_declared: Vec<(usize, &'a str)>,
+	/// registered namespaces: prefix and URI, see [`XmlWriter::register_ns`]
+	ns_registry: Vec<(&'a str, &'a str)>,
 	buffer: Box<Buffer>,
 	/// An XML namespace that all elements will be part of, unless `None`
 	namespace: Option<&'a str>,
-	/// If `true` it will
-	/// - indent all opening elements on a new line
-	/// - put closing elements into own line
-	/// - elements without children are automatically self-closing
-	pretty: bool,
+	/// if `true` unprefixed elements are within a declared default namespace
+	default_namespace: bool,
+	/// if `true` a default namespace is declared in scope
+	default_declared: bool,
+	/// default namespace scopes: depth of the elem opening the scope and the state to restore when it closes
+	default_ns_scopes: Vec<(usize, bool, bool)>,
+	/// the configuration
+	config: Config,
 	/// if `true` an element is open
 	opened: bool,
 	/// newline/indentation indicator
 	newline: bool,
+	/// if `true` the next indentation is skipped, see [`XmlWriter::suppress_next_indent`]
+	skip_indent: bool,
+	/// depth of the elem whose content is written without indentation, see [`XmlWriter::verbatim`]
+	preserve_depth: Option<usize>,
+	/// if `true` a direct child of the root element has been written
+	top_level_child: bool,
+	/// if `true` an element has been written
+	has_root: bool,
+	/// if `true` more than one top level element has been written
+	multiple_roots: bool,
+	/// staged attributes of the open elements, if attributes are sorted or namespace declarations ordered
+	staged: Vec<u8>,
+	/// sort key, depth of the elem and span within `staged` for each staged attribute,
+	/// the group of namespace declarations and attributes is compared first
+	staged_spans: Vec<((bool, String), usize, usize, usize)>,
+	/// number of staged attributes already written, they are kept while their elem is open for a rollback
+	staged_flushed: usize,
+	/// if `true` writes go to `staged`
+	staging: bool,
+	/// if `true` the attributes of the open element are not sorted
+	ordered: bool,
+	/// output not yet handed to the buffer, see [`XmlWriter::set_buffer_size`]
+	pending: Vec<u8>,
+	/// number of bytes handed to the buffer so far, including pending output
+	bytes_written: usize,
+	/// value of `bytes_written` at the last flush
+	last_flush_count: usize,
+	/// if `true` the maximum output size has been reached and further output is discarded
+	truncated: bool,
+	/// recorded events, `None` if recording is disabled
+	events: Option<Vec<Event<'a>>>,
+	/// start of each open element, in sync with the element stack
+	elem_starts: Vec<ElemStart>,
+	/// start and end, in bytes written, of the last closed element
+	last_elem: Option<(ElemStart, usize)>,
+	/// end, in bytes written, of the last text content
+	text_end: Option<usize>,
+	/// callback adding attributes to each element
+	decorator: Option<ElementDecorator<'a, Buffer>>,
+	/// receiver of the events of all high-level operations
+	event_sink: Option<Box<dyn EventSink<'a> + Send + 'a>>,
+}
+
+/// The state before the start tag of an element, see [`XmlWriter::pop_last_element`].
+#[derive(Clone, Copy, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+struct ElemStart {
+	newline: bool,
+	skip_indent: bool,
+	top_level_child: bool,
+	has_root: bool,
+	multiple_roots: bool,
+	bytes_written: usize,
+	events: usize,
+}
+
+/// A snapshot of the [`XmlWriter`]s state, see [`XmlWriter::checkpoint`].
+#[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Checkpoint<'a> {
+	stack: Vec<(&'a str, bool)>,
+	ns_stack: Vec<Option<&'a str>>,
+	ns_scopes: Vec<(usize, Option<&'a str>)>,
+	ns_declared: Vec<(usize, &'a str)>,
+	namespace: Option<&'a str>,
+	default_namespace: bool,
+	default_declared: bool,
+	default_ns_scopes: Vec<(usize, bool, bool)>,
+	opened: bool,
+	newline: bool,
+	skip_indent: bool,
+	preserve_depth: Option<usize>,
+	top_level_child: bool,
+	has_root: bool,
+	multiple_roots: bool,
+	bytes_written: usize,
+	staged: usize,
+	staged_spans: usize,
+	staged_flushed: usize,
+	ordered: bool,
+	events: usize,
+	elem_starts: Vec<ElemStart>,
+}
+
+/// A token for an elem opened with [`XmlWriter::open`],
+/// which has to be handed to [`XmlWriter::end_elem_checked`] to close that elem.
+#[derive(Debug, PartialEq, Eq)]
+#[must_use]
+pub struct OpenElem<'a> {
+	name: &'a str,
+	depth: usize,
+}
+
+impl<'a> OpenElem<'a> {
+	/// Get the name of the elem
+	#[must_use]
+	pub const fn name(&self) -> &'a str {
+		self.name
+	}
+
+	/// Get the depth of the elem, the root elem has a depth of 1
+	#[must_use]
+	pub const fn depth(&self) -> usize {
+		self.depth
+	}
+}
+
+/// Adapter escaping formatted text on the fly, see [`XmlWriter::text_fmt`].
+struct TextEscaper<'w, 'a, W: Write> {
+	writer: &'w mut XmlWriter<'a, W>,
+	/// the error of the writer, as [`core::fmt::Error`] can not carry it
+	error: Option<Error>,
+	/// a normalized `\r` at the end of a piece, which may be followed by a `\n`
+	pending_cr: bool,
+}
+
+impl<W: Write> core::fmt::Write for TextEscaper<'_, '_, W> {
+	fn write_str(&mut self, s: &str) -> core::fmt::Result {
+		self.writer
+			.escape_text_piece(s, &mut self.pending_cr)
+			.map_err(|error| {
+				self.error = Some(error);
+				core::fmt::Error
+			})
+	}
 }
 
 impl<Buffer: Write> core::fmt::Debug for XmlWriter<'_, Buffer> {
 	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-		write!(f, "XmlWriter {{ stack: {:?}, opened: {} }}", self.stack, self.opened)
+		f.debug_struct("XmlWriter")
+			.field("stack", &self.stack)
+			.field("opened", &self.opened)
+			.field("pretty", &self.config.pretty)
+			.field("newline", &self.newline)
+			.field("len", &self.buffer.len())
+			.field("pending", &self.pending.len())
+			.finish()
 	}
 }
 
@@ -55,14 +242,7 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 	/// - omit all indentations and newlines
 	/// - elements without children are automatically self-closing
 	pub fn compact_mode(buffer: W) -> Self {
-		XmlWriter {
-			stack: Vec::new(),
-			buffer: Box::new(buffer),
-			namespace: None,
-			pretty: false,
-			opened: false,
-			newline: false,
-		}
+		Self::with_config(buffer, Config::compact())
 	}
 
 	/// Create a new writer with `pretty` output which will
@@ -70,24 +250,589 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 	/// - put closing elements into own line
 	/// - elements without children are automatically self-closing
 	pub fn pretty_mode(buffer: W) -> Self {
+		Self::with_config(buffer, Config::pretty())
+	}
+
+	/// Create a new writer with `canonical` output for signing or hashing, a "C14N-lite" which
+	/// - omits all indentations and newlines
+	/// - writes elements without children as `<x></x>`
+	/// - sorts namespace declarations by prefix, followed by attributes sorted by qualified name
+	/// - writes attribute values within double quotes
+	/// - escapes `&`, `<`, `>` and `\r` in text and `&`, `<`, `"`, `\t`, `\n` and `\r` in attribute values
+	///   as required by [Canonical XML](https://www.w3.org/TR/xml-c14n)
+	/// - writes CDATA sections as escaped text
+	///
+	/// Not covered are
+	/// - sorting attributes by namespace URI instead of prefix
+	/// - removal of superfluous namespace declarations
+	/// - comments, which are escaped like in the other modes
+	/// - content written with [`XmlWriter::write`] or an XML declaration written with [`XmlWriter::dtd`]
+	pub fn canonical_mode(buffer: W) -> Self {
+		Self::with_config(buffer, Config::canonical())
+	}
+
+	/// Create a new writer with the given [`Config`]
+	pub fn with_config(buffer: W, config: Config) -> Self {
+		let pending = Vec::with_capacity(config.buffer_size);
 		XmlWriter {
 			stack: Vec::new(),
+			ns_stack: Vec::new(),
+			ns_scopes: Vec::new(),
+			ns_declared: Vec::new(),
+			ns_registry: Vec::new(),
 			buffer: Box::new(buffer),
 			namespace: None,
-			pretty: true,
+			default_namespace: false,
+			default_declared: false,
+			default_ns_scopes: Vec::new(),
+			config,
 			opened: false,
 			newline: false,
+			skip_indent: false,
+			preserve_depth: None,
+			top_level_child: false,
+			has_root: false,
+			multiple_roots: false,
+			staged: Vec::new(),
+			staged_spans: Vec::new(),
+			staged_flushed: 0,
+			staging: false,
+			ordered: false,
+			pending,
+			bytes_written: 0,
+			last_flush_count: 0,
+			truncated: false,
+			events: None,
+			elem_starts: Vec::new(),
+			last_elem: None,
+			text_end: None,
+			decorator: None,
+			event_sink: None,
 		}
 	}
 
+	/// Get the configuration
+	#[must_use]
+	pub const fn config(&self) -> &Config {
+		&self.config
+	}
+
+	/// Returns `true` in `pretty` mode, `false` in `compact` mode,
+	/// e.g. for helpers adapting the formatting of their own output.
+	#[must_use]
+	pub const fn is_pretty(&self) -> bool {
+		self.config.pretty
+	}
+
 	/// Switch to `compact` mode
 	pub const fn set_compact_mode(&mut self) {
-		self.pretty = false;
+		self.config.pretty = false;
 	}
 
 	/// Switch to `pretty` mode
 	pub const fn set_pretty_mode(&mut self) {
-		self.pretty = true;
+		self.config.pretty = true;
+	}
+
+	/// Set the indentation per level in `pretty` mode, default is [`IndentUnit::Spaces(2)`](IndentUnit::Spaces)
+	pub const fn set_indent(&mut self, unit: IndentUnit) {
+		self.config.indent = unit.as_str();
+	}
+
+	/// Enable/disable a single space between sibling nodes in `compact` mode, which is disabled by default.
+	/// Like the line breaks in `pretty` mode the space is left out around text content,
+	/// e.g. `<a> <b/> <c>text</c> </a>`.
+	pub const fn set_compact_spacing(&mut self, enabled: bool) {
+		self.config.compact_spacing = enabled;
+	}
+
+	/// Enable/disable blank lines between the direct children of the root element in `pretty` mode
+	pub const fn set_blank_line_between_top_level(&mut self, enabled: bool) {
+		self.config.blank_line_top_level = enabled;
+	}
+
+	/// Enable/disable a newline after the last element closes in `pretty` mode, which is disabled by default
+	pub const fn set_final_newline(&mut self, enabled: bool) {
+		self.config.final_newline = enabled;
+	}
+
+	/// Set the size of an internal buffer collecting output before it is handed to the buffer of the writer,
+	/// which reduces the number of calls to unbuffered sinks. A size of `0`, the default, disables it.
+	/// Pending output is handed over when the internal buffer is full and on [`XmlWriter::flush`].
+	pub fn set_buffer_size(&mut self, size: usize) {
+		self.config.buffer_size = size;
+		self.pending
+			.reserve(size.saturating_sub(self.pending.len()));
+	}
+
+	/// Enable/disable validation of element and attribute names against the
+	/// [Name](https://www.w3.org/TR/xml/#NT-Name) production, which is disabled by default.
+	/// Invalid names are rejected with [`Error::InvalidName`].
+	pub const fn set_validate_names(&mut self, enabled: bool) {
+		self.config.validate_names = enabled;
+	}
+
+	/// Enable/disable rejection of element and attribute names starting with `xml` in any case,
+	/// which are reserved by the XML specification, e.g. `xmlData`. This is disabled by default.
+	/// The names `xml:lang`, `xml:space`, `xml:base`, `xml:id` and namespace declarations are accepted.
+	/// Reserved names are rejected with [`Error::ReservedName`].
+	pub const fn set_reject_reserved_names(&mut self, enabled: bool) {
+		self.config.reject_reserved_names = enabled;
+	}
+
+	/// Enable/disable checking of used namespace prefixes against the declarations in scope,
+	/// which is disabled by default.
+	/// Prefixes are checked by methods relying on a standard or registered prefix like [`XmlWriter::elem_nil`]
+	/// and [`XmlWriter::attr_ns_uri`],
+	/// undeclared prefixes are rejected with [`Error::UndeclaredNamespace`].
+	pub const fn set_track_namespaces(&mut self, enabled: bool) {
+		self.config.track_namespaces = enabled;
+	}
+
+	/// Check that `prefix` is declared, if namespaces are tracked
+	fn check_declared(&self, prefix: &str) -> Result<(), Error> {
+		if self.config.track_namespaces
+			&& prefix != "xml"
+			&& !self
+				.ns_declared
+				.iter()
+				.any(|(_, declared)| *declared == prefix)
+		{
+			return Err(Error::UndeclaredNamespace {
+				namespace: prefix.into(),
+			});
+		}
+		Ok(())
+	}
+
+	/// Check a name, if names are validated or reserved names are rejected
+	fn check_name(&self, name: &str) -> Result<(), Error> {
+		if self.config.validate_names && !is_name(name) {
+			return Err(Error::InvalidName);
+		}
+		self.check_reserved(name)
+	}
+
+	/// Check that a name is not reserved, if reserved names are rejected
+	fn check_reserved(&self, name: &str) -> Result<(), Error> {
+		if self.config.reject_reserved_names && is_reserved_name(name) {
+			return Err(Error::ReservedName { name: name.into() });
+		}
+		Ok(())
+	}
+
+	/// Enable/disable document mode, which is disabled by default.
+	/// In document mode [`XmlWriter::finish`] and [`XmlWriter::try_into_inner`] require exactly one root element,
+	/// otherwise the output is a fragment, which may be empty or have several top level elements.
+	pub const fn set_document_mode(&mut self, enabled: bool) {
+		self.config.document = enabled;
+	}
+
+	/// Enable/disable a lightweight check of the output structure in [`XmlWriter::finish`],
+	/// which is disabled by default. It checks for balanced tags, and in document mode,
+	/// see [`XmlWriter::set_document_mode`], for a single root element without content outside of it.
+	/// This catches malformed output from raw writes like [`XmlWriter::write`].
+	/// The check is only possible for Writers providing their content, see [`Write::as_bytes`],
+	/// and covers only the output held by the Writer, e.g. not output taken by [`XmlWriter::take_buffer`].
+	pub const fn set_validate_on_finish(&mut self, enabled: bool) {
+		self.config.validate_on_finish = enabled;
+	}
+
+	/// Enable/disable writing elements without children as `<x></x>` instead of `<x/>`
+	pub const fn set_expand_empty_elements(&mut self, enabled: bool) {
+		self.config.expand_empty = enabled;
+	}
+
+	/// Set the escaping of apostrophes in text and attribute values, default is [`ApostropheStyle::Apos`].
+	/// As HTML4 does not know `&apos;`, use [`ApostropheStyle::NumericDec`] or [`ApostropheStyle::None`]
+	/// for XHTML which is served as `text/html`.
+	pub const fn set_apostrophe_escape(&mut self, style: ApostropheStyle) {
+		self.config.apostrophe = style;
+	}
+
+	/// Enable/disable sorting of attributes.
+	/// If enabled, attributes are collected until the element tag is closed
+	/// and written with namespace declarations first, each sorted by name.
+	pub const fn set_sort_attributes(&mut self, enabled: bool) {
+		self.config.sort_attributes = enabled;
+	}
+
+	/// Set the order of namespace declarations relative to the other attributes of an element,
+	/// default is [`NsOrder::AsWritten`], with [`NsOrder::Before`] namespace declarations come first regardless of the call order.
+	/// If not written as is, attributes are collected until the element tag is closed like for sorting,
+	/// see [`XmlWriter::set_sort_attributes`], an elem begun with [`XmlWriter::begin_elem_ordered`]
+	/// keeps the call order.
+	pub const fn set_ns_decl_order(&mut self, order: NsOrder) {
+		self.config.ns_order = order;
+	}
+
+	/// Enable/disable normalization of line endings, which is disabled by default.
+	/// When enabled, `\r\n` and a lone `\r` are written as `\n` in text content.
+	/// In attribute values, where a parser would replace them by a space,
+	/// all line endings are written as the character reference `&#10;`.
+	/// This makes the output match what a parser reproduces.
+	pub const fn set_normalize_newlines(&mut self, enabled: bool) {
+		self.config.normalize_newlines = enabled;
+	}
+
+	/// Enable/disable strict escaping, which is disabled by default.
+	/// By default `&`, `<`, `>` and quotes are escaped in text content and attribute values.
+	/// When enabled, only the characters required by the XML specification are escaped:
+	/// `&`, `<` and the delimiting quote in attribute values, `&` and `<` in text content.
+	/// In text content a `>` is still escaped where it might complete a `]]>`.
+	/// This gives minimal output, canonical mode keeps its own escaping rules.
+	pub const fn set_strict_escaping(&mut self, enabled: bool) {
+		self.config.strict_escaping = enabled;
+	}
+
+	/// Set a sanitizer which checks all attribute values before they are written.
+	/// If the sanitizer returns an error, e.g. [`Error::RejectedAttrValue`],
+	/// the attribute is not written and the error is returned.
+	pub fn set_attr_sanitizer(&mut self, sanitizer: AttrSanitizer) {
+		self.config.attr_sanitizer = Some(sanitizer);
+	}
+
+	/// Remove the attribute value sanitizer
+	pub fn unset_attr_sanitizer(&mut self) {
+		self.config.attr_sanitizer = None;
+	}
+
+	/// Set an observer which sees all output, e.g. to log or hash it, before it is handed to the buffer.
+	/// Output removed again by [`XmlWriter::rollback`] has been observed nevertheless.
+	pub fn set_write_observer(&mut self, observer: WriteObserver) {
+		self.config.write_observer = Some(observer);
+	}
+
+	/// Remove the write observer
+	pub fn unset_write_observer(&mut self) {
+		self.config.write_observer = None;
+	}
+
+	/// Set an observer called with the name and the resulting depth of each element closed with [`XmlWriter::end_elem`],
+	/// also when closed by [`XmlWriter::close`] or [`XmlWriter::close_to`], e.g. to report the progress.
+	/// Elements written as a whole like with [`XmlWriter::empty_elem`] or [`XmlWriter::elem_text`] are observed as well.
+	pub fn set_close_observer(&mut self, observer: CloseObserver) {
+		self.config.close_observer = Some(observer);
+	}
+
+	/// Remove the close observer
+	pub fn unset_close_observer(&mut self) {
+		self.config.close_observer = None;
+	}
+
+	/// Set a decorator called with the name of each element written with [`XmlWriter::begin_elem`],
+	/// [`XmlWriter::empty_elem`] or [`XmlWriter::elem`] while its start tag is open,
+	/// so that it can add attributes, e.g. a generated `uid` to every element.
+	/// The decorator may only write attributes, it is not called for elements it writes itself.
+	/// If it fails for an element without children, the start tag is closed with the attributes written so far.
+	/// ```
+	/// use woxml::{Error, XmlWriter};
+	///
+	/// fn uid(xml: &mut XmlWriter<Vec<u8>>, name: &str) -> Result<(), Error> {
+	///     if name != "root" {
+	///         xml.attr("uid", "1")?;
+	///     }
+	///     Ok(())
+	/// }
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.set_element_decorator(uid);
+	/// xml.begin_elem("root")?;
+	/// xml.empty_elem("child")?;
+	/// xml.end_elem()?;
+	/// assert_eq!(String::try_from(xml)?, r#"<root><child uid="1"/></root>"#);
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	pub fn set_element_decorator(&mut self, decorator: ElementDecorator<'a, W>) {
+		self.decorator = Some(decorator);
+	}
+
+	/// Remove the element decorator
+	pub fn unset_element_decorator(&mut self) {
+		self.decorator = None;
+	}
+
+	/// Call the element decorator for the open start tag of the elem `name`.
+	/// The decorator is taken while it is called, so it is not called recursively.
+	/// # Errors
+	/// - any error returned by the decorator
+	/// - if the decorator wrote other content than attributes
+	fn decorate(&mut self, name: &str) -> Result<(), Error> {
+		if let Some(decorator) = self.decorator.take() {
+			let depth = self.stack.len();
+			let result = decorator(self, name);
+			self.decorator = Some(decorator);
+			result?;
+			if !self.opened || self.stack.len() != depth {
+				return Err(Error::DecoratorWroteContent);
+			}
+		}
+		Ok(())
+	}
+
+	/// Call the element decorator for the start tag of an elem without children,
+	/// which is open while the decorator is called, and finish the tag.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - see [`XmlWriter::decorate`], the tag is finished with the attributes written so far in that case
+	fn end_leaf(&mut self, namespace: Option<&'a str>, name: &str) -> Result<(), Error> {
+		let mut decorated = Ok(());
+		if self.decorator.is_some() {
+			let staged = self.staged_spans.len();
+			self.opened = true;
+			decorated = self.decorate(name);
+			let written = self.write_staged();
+			// the elem is closed right away
+			self.discard_staged(staged);
+			self.ordered = false;
+			self.opened = false;
+			written?;
+		}
+		self.end_empty(namespace, name)?;
+		self.observe_close(name);
+		decorated
+	}
+
+	/// Call the close observer, if any, for the elem `name` just closed
+	fn observe_close(&self, name: &str) {
+		if let Some(observer) = self.config.close_observer {
+			observer(name, self.stack.len());
+		}
+	}
+
+	/// Set the maximum length of attribute values in bytes, which is unlimited by default.
+	/// Longer values are not written but result in [`Error::AttrValueTooLong`],
+	/// e.g. to guard against pathological untrusted input.
+	/// Of a streamed value the chunks read before are written, see [`XmlWriter::attr_streamed`].
+	pub const fn set_max_attr_value_len(&mut self, max: usize) {
+		self.config.max_attr_value_len = max;
+	}
+
+	/// Check the length of an attribute value against the maximum
+	const fn check_attr_value_len(&self, len: usize) -> Result<(), Error> {
+		let max = self.config.max_attr_value_len;
+		if len > max {
+			return Err(Error::AttrValueTooLong { len, max });
+		}
+		Ok(())
+	}
+
+	/// Write an attribute with `f`, staging it for sorting or ordering of namespace declarations if enabled.
+	/// The sort key is the concatenation of `key`.
+	/// # Errors
+	/// - any error returned by `f`, the staged attribute is discarded in that case
+	fn staged_attr<F: FnOnce(&mut Self) -> Result<(), Error>>(
+		&mut self,
+		namespace_decl: bool,
+		key: &[&str],
+		f: F,
+	) -> Result<(), Error> {
+		let sort = self.config.sort_attributes;
+		let group = match self.config.ns_order {
+			NsOrder::Before => !namespace_decl,
+			NsOrder::After => namespace_decl,
+			NsOrder::AsWritten if sort => !namespace_decl,
+			NsOrder::AsWritten => return f(self),
+		};
+		if self.ordered {
+			return f(self);
+		}
+		let start = self.staged.len();
+		self.staging = true;
+		let result = f(self);
+		self.staging = false;
+		if result.is_err() {
+			self.staged.truncate(start);
+			return result;
+		}
+		// unsorted attributes keep their order within the group, as sorting is stable
+		let key = if sort { key.concat() } else { String::new() };
+		self.staged_spans
+			.push(((group, key), self.stack.len(), start, self.staged.len()));
+		Ok(())
+	}
+
+	/// Write the staged attributes sorted or ordered.
+	/// # Errors
+	/// - if writing to buffer fails
+	fn write_staged(&mut self) -> Result<(), Error> {
+		let from = self.staged_flushed;
+		if self.staged_spans.len() == from {
+			return Ok(());
+		}
+		let staged = core::mem::take(&mut self.staged);
+		let mut spans = core::mem::take(&mut self.staged_spans);
+		spans[from..].sort_by(|(a, ..), (b, ..)| a.cmp(b));
+		let result = spans[from..]
+			.iter()
+			.try_for_each(|(_, _, start, end)| self.write_slice(&staged[*start..*end]));
+		self.staged = staged;
+		self.staged_flushed = spans.len();
+		self.staged_spans = spans;
+		result
+	}
+
+	/// Discard the staged attributes from the `len`th on.
+	fn discard_staged(&mut self, len: usize) {
+		if let Some((_, _, start, _)) = self.staged_spans.get(len) {
+			self.staged.truncate(*start);
+		}
+		self.staged_spans.truncate(len);
+		self.staged_flushed = self.staged_flushed.min(len);
+	}
+
+	/// Set the maximum size of the output in bytes, which is unlimited by default.
+	/// Output which would exceed it is discarded together with all further output,
+	/// so that write methods become no-ops instead of failing, see [`XmlWriter::is_truncated`].
+	/// By default the output is cut where the maximum is reached, even within a tag,
+	/// see [`XmlWriter::set_truncation_headroom`] to keep it well-formed.
+	pub const fn set_max_output_bytes(&mut self, max: usize) {
+		self.config.max_output_bytes = max;
+	}
+
+	/// Reserve `headroom` bytes within the maximum output size, see [`XmlWriter::set_max_output_bytes`],
+	/// to close the open elements on truncation, which is `0` and disabled by default.
+	/// On truncation the output is cut after its last complete tag or text
+	/// and end tags for the elements open there are written, if they fit into the maximum output size.
+	/// This requires a buffer which can provide its bytes and be truncated like `Vec<u8>`,
+	/// otherwise the write causing the truncation fails with [`Error::TruncateUnsupported`].
+	pub const fn set_truncation_headroom(&mut self, headroom: usize) {
+		self.config.truncation_headroom = headroom;
+	}
+
+	/// Returns `true` if output has been discarded because the maximum output size has been reached,
+	/// see [`XmlWriter::set_max_output_bytes`].
+	#[must_use]
+	pub const fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	/// Cut truncated output after its last complete construct and close the elements open there,
+	/// if the end tags fit into the maximum output size.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the buffer can not provide its bytes or does not support truncation
+	fn close_truncated(&mut self) -> Result<(), Error> {
+		let written = self
+			.buffer
+			.as_bytes()
+			.ok_or(Error::TruncateUnsupported)?;
+		let bytes = if self.pending.is_empty() {
+			Cow::Borrowed(written)
+		} else {
+			Cow::Owned([written, &self.pending].concat())
+		};
+		let (cut, open) = scanner::last_complete(&bytes);
+		let excess = bytes.len() - cut;
+		let end_tags: Vec<u8> = open
+			.iter()
+			.rev()
+			.flat_map(|name| [SELF_CLOSE_OPEN.as_bytes(), name, CLOSE.as_bytes()])
+			.flatten()
+			.copied()
+			.collect();
+		let bytes_written = self
+			.bytes_written
+			.checked_sub(excess)
+			.ok_or(Error::InvalidCheckpoint)?;
+		self.truncate_output(bytes_written)?;
+		self.bytes_written = bytes_written;
+		if bytes_written.saturating_add(end_tags.len()) <= self.config.max_output_bytes {
+			self.emit(&end_tags)?;
+		}
+		Ok(())
+	}
+
+	/// Check an attribute value before writing it
+	fn check_attr_value(&self, value: &str) -> Result<(), Error> {
+		self.check_attr_value_len(value.len())?;
+		if let Some(sanitizer) = self.config.attr_sanitizer {
+			sanitizer(value)?;
+		}
+		Ok(())
+	}
+
+	/// Enable/disable recording of [`Event`]s for all high-level operations.
+	/// Recording is disabled by default, disabling it discards all events recorded so far.
+	pub fn set_record_events(&mut self, enabled: bool) {
+		if enabled {
+			if self.events.is_none() {
+				self.events = Some(Vec::new());
+			}
+		} else {
+			self.events = None;
+		}
+	}
+
+	/// Take the [`Event`]s recorded so far.
+	/// Returns an empty [`Vec`] if recording is disabled.
+	pub fn take_events(&mut self) -> Vec<Event<'a>> {
+		self.events
+			.as_mut()
+			.map(core::mem::take)
+			.unwrap_or_default()
+	}
+
+	/// Set a sink receiving the [`Event`]s of all high-level operations, in addition to the output,
+	/// e.g. to build a DOM alongside. Events already sent are not retracted by [`XmlWriter::rollback`].
+	/// For a writer without output see [`XmlWriter::event_mode`].
+	/// Like the writer the sink has to be [`Send`], but needs not to be [`Sync`], e.g. using a [`core::cell::Cell`].
+	pub fn set_event_sink(&mut self, sink: impl EventSink<'a> + Send + 'a) {
+		self.event_sink = Some(Box::new(sink));
+	}
+
+	/// Remove the event sink
+	pub fn unset_event_sink(&mut self) {
+		self.event_sink = None;
+	}
+
+	/// Whether events are recorded or sent to an event sink
+	const fn recording(&self) -> bool {
+		self.events.is_some() || self.event_sink.is_some()
+	}
+
+	/// Record an event, if recording is enabled, and send it to the event sink, if there is one
+	/// # Errors
+	/// - if the event sink fails
+	fn record(&mut self, event: impl FnOnce() -> Event<'a>) -> Result<(), Error> {
+		match (self.events.as_mut(), self.event_sink.as_mut()) {
+			(None, None) => {}
+			(Some(events), None) => events.push(event()),
+			(None, Some(sink)) => sink.event(event())?,
+			(Some(events), Some(sink)) => {
+				let event = event();
+				sink.event(event.clone())?;
+				events.push(event);
+			}
+		}
+		Ok(())
+	}
+
+	/// Record the start and end of an element without children
+	/// # Errors
+	/// - if the event sink fails
+	fn record_leaf(&mut self, name: &str) -> Result<(), Error> {
+		self.record_in(self.namespace, name, None)
+	}
+
+	/// Record the start and end of an element within `namespace` with optional text content
+	/// # Errors
+	/// - if the event sink fails
+	fn record_in(&mut self, namespace: Option<&'a str>, name: &str, text: Option<&str>) -> Result<(), Error> {
+		if !self.recording() {
+			return Ok(());
+		}
+		self.record(|| Event::StartElem {
+			namespace,
+			name: Cow::Owned(name.into()),
+		})?;
+		if let Some(text) = text {
+			self.record(|| Event::Text(text.into()))?;
+		}
+		self.record(|| Event::EndElem {
+			namespace,
+			name: Cow::Owned(name.into()),
+		})
 	}
 
 	/// Get the namespace
@@ -106,6 +851,57 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 		self.namespace = None;
 	}
 
+	/// Whether the current subtree is within a declared default namespace
+	#[must_use]
+	pub const fn default_namespace_active(&self) -> bool {
+		self.default_namespace
+	}
+
+	/// Mark whether the current subtree is within a default namespace declared via `xmlns="..."`,
+	/// until the currently open elem is closed. Declaring a default namespace with [`XmlWriter::ns_decl`]
+	/// activates it as well, e.g. for a subtree within a default namespace declared by a surrounding document.
+	/// Deactivating a default namespace within an elem declares it empty with `xmlns=""`,
+	/// so that the unprefixed elements of the subtree are in no namespace.
+	/// In contrast to [`XmlWriter::set_namespace`] no prefix is added to element names.
+	/// Elements written while a namespace prefix is set belong to the prefixed namespace,
+	/// only unprefixed elements belong to the default namespace.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::UndeclaredNamespace`] when activating without a default namespace declared in scope
+	///   and namespaces are tracked, see [`XmlWriter::set_track_namespaces`]
+	/// - [`Error::OpenNamespaceWithoutElement`] when deactivating within an elem whose start tag is closed
+	pub fn set_default_namespace_active(&mut self, active: bool) -> Result<(), Error> {
+		if active == self.default_namespace {
+			return Ok(());
+		}
+		if active && self.config.track_namespaces && !self.default_declared {
+			return Err(Error::UndeclaredNamespace {
+				namespace: String::new(),
+			});
+		}
+		if !active && !self.stack.is_empty() {
+			return self.ns_decl_iter([(None, "")]);
+		}
+		self.scope_default_namespace(active, self.default_declared);
+		Ok(())
+	}
+
+	/// Set whether unprefixed elems are within a default namespace and whether it is declared
+	/// until the open elem is closed
+	fn scope_default_namespace(&mut self, active: bool, declared: bool) {
+		// a scope already opened with the elem keeps the state to restore
+		if self
+			.default_ns_scopes
+			.last()
+			.is_none_or(|(depth, ..)| *depth < self.stack.len())
+		{
+			self.default_ns_scopes
+				.push((self.stack.len(), self.default_namespace, self.default_declared));
+		}
+		self.default_namespace = active;
+		self.default_declared = declared;
+	}
+
 	/// Write the DTD.
 	/// # Errors
 	/// - if writing to buffer fails
@@ -115,20 +911,115 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 		self.write("\" ?>\n")
 	}
 
+	/// Write a complete document type declaration like `<!DOCTYPE html>` as is in the prolog,
+	/// followed by a newline in `pretty` mode. The caller is responsible for its validity.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::ContentAlreadyWritten`] if an element has been written before, nothing is written in that case
+	pub fn doctype_raw(&mut self, doctype: &str) -> Result<(), Error> {
+		if self.has_root {
+			return Err(Error::ContentAlreadyWritten);
+		}
+		self.indent()?;
+		self.write(doctype)?;
+		if self.config.pretty {
+			self.write(self.config.line_ending)?;
+			self.newline = false;
+		}
+		Ok(())
+	}
+
+	/// Start a document: write the DTD, open the root element and declare its namespaces.
+	/// ```
+	/// use woxml::XmlWriter;
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.document("UTF-8", "root", &vec![(None, "urn:example")])?;
+	/// xml.end_elem()?;
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if something has been written before, nothing is written in that case
+	/// - if the root name or a prefix is invalid, see [`XmlWriter::begin_elem`] and [`XmlWriter::ns_decl`]
+	pub fn document(
+		&mut self,
+		encoding: &str,
+		root: &'a str,
+		ns_map: &Vec<(Option<&'a str>, &'a str)>,
+	) -> Result<(), Error> {
+		if self.bytes_written > 0 {
+			return Err(Error::ContentAlreadyWritten);
+		}
+		self.dtd(encoding)?;
+		self.begin_elem(root)?;
+		self.ns_decl(ns_map)
+	}
+
 	fn indent(&mut self) -> Result<(), Error> {
-		if self.pretty {
+		if core::mem::take(&mut self.skip_indent) {
+			// the following content starts on a new line again
+			self.newline = true;
+			return Ok(());
+		}
+		let spacing = !self.config.pretty && self.config.compact_spacing;
+		if (self.config.pretty || spacing)
+			&& self
+				.preserve_depth
+				.is_none_or(|depth| self.stack.len() < depth)
+		{
 			if self.newline {
-				self.write("\n")?;
+				self.write(if spacing { SPACE } else { self.config.line_ending })?;
 			} else {
 				self.newline = true;
 			}
-			for _ in 0..self.stack.len() {
-				self.write("  ")?;
+			if !spacing {
+				for _ in 0..self.stack.len() {
+					self.write(self.config.indent)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Write a newline after a closed top level element, if enabled in `pretty` mode
+	fn final_newline(&mut self) -> Result<(), Error> {
+		if self.config.pretty && self.config.final_newline && self.stack.is_empty() {
+			self.write(self.config.line_ending)?;
+			// the next top level element must not start with another newline
+			self.newline = false;
+		}
+		Ok(())
+	}
+
+	/// Write a blank line before a direct child of the root element, except for the first one,
+	/// if enabled in `pretty` mode
+	fn top_level_gap(&mut self) -> Result<(), Error> {
+		if self.config.pretty && self.config.blank_line_top_level {
+			match self.stack.len() {
+				0 => self.top_level_child = false,
+				1 => {
+					if self.top_level_child {
+						self.write(self.config.line_ending)?;
+					} else {
+						self.top_level_child = true;
+					}
+				}
+				_ => {}
 			}
 		}
 		Ok(())
 	}
 
+	/// Write the `<` of a start tag
+	fn start_tag(&mut self) -> Result<(), Error> {
+		if self.stack.is_empty() && self.has_root {
+			self.multiple_roots = true;
+		}
+		self.has_root = true;
+		self.write(OPEN)
+	}
+
 	/// Write a namespace prefix for the current element,
 	/// if there is one set
 	fn ns_prefix(&mut self, namespace: Option<&'a str>) -> Result<(), Error> {
@@ -140,19 +1031,80 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 	}
 
 	/// Writes namespace declarations (xmlns:xx) into the currently open element.
+	/// The namespace URIs are escaped.
+	/// # Errors
+	/// see [`XmlWriter::ns_decl_iter`]
+	pub fn ns_decl(&mut self, ns_map: &Vec<(Option<&'a str>, &'a str)>) -> Result<(), Error> {
+		self.ns_decl_iter(ns_map.iter().copied())
+	}
+
+	/// Writes namespace declarations (xmlns:xx) from any iterable into the currently open element,
+	/// e.g. an array `[(None, "urn:default"), (Some("st"), "urn:st")]`.
+	/// The namespace URIs are escaped.
 	/// # Errors
 	/// - if writing to buffer fails
 	/// - when opening a namespace without having an element
-	pub fn ns_decl(&mut self, ns_map: &Vec<(Option<&'a str>, &'a str)>) -> Result<(), Error> {
+	/// - if a prefix is not a valid `NCName`, nothing is written in that case
+	/// - if a prefix is declared twice, nothing is written in that case
+	/// - if the reserved prefix `xmlns` is declared or `xml` is bound to another than its standard URI,
+	///   nothing is written in that case
+	pub fn ns_decl_iter<I>(&mut self, ns_map: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (Option<&'a str>, &'a str)>,
+		I::IntoIter: Clone,
+	{
 		if !self.opened {
 			return Err(Error::OpenNamespaceWithoutElement);
 		}
+		let ns_map = ns_map.into_iter();
+		if ns_map
+			.clone()
+			.any(|(prefix, _)| prefix.is_some_and(|prefix| !is_ncname(prefix)))
+		{
+			return Err(Error::InvalidName);
+		}
+		if let Some((prefix, _)) = ns_map
+			.clone()
+			.enumerate()
+			.find(|(index, (prefix, _))| {
+				ns_map
+					.clone()
+					.take(*index)
+					.any(|(other, _)| other == *prefix)
+			})
+			.map(|(_, entry)| entry)
+		{
+			return Err(Error::DuplicateNamespacePrefix {
+				prefix: prefix.unwrap_or_default().into(),
+			});
+		}
+		if let Some((Some(prefix), _)) = ns_map
+			.clone()
+			.find(|(prefix, uri)| prefix.is_some_and(|prefix| is_reserved_prefix(prefix, uri)))
+		{
+			return Err(Error::ReservedPrefix { prefix: prefix.into() });
+		}
 
-		for item in ns_map {
-			let name = item
-				.0
-				.map_or_else(|| "xmlns".to_string(), |pre| "xmlns:".to_string() + pre);
-			self.attr(&name, item.1)?;
+		for (prefix, uri) in ns_map {
+			self.record(|| Event::Attr {
+				name: prefix.map_or_else(|| "xmlns".into(), |prefix| ["xmlns:", prefix].concat()),
+				value: uri.into(),
+			})?;
+			self.staged_attr(true, &[prefix.unwrap_or_default()], |xml| {
+				xml.write(" xmlns")?;
+				if let Some(prefix) = prefix {
+					xml.write(":")?;
+					xml.write(prefix)?;
+				}
+				xml.write(xml.config.quote.open())?;
+				xml.escape(uri, Escape::Value)?;
+				xml.write(xml.config.quote.close())
+			})?;
+			if let Some(prefix) = prefix {
+				self.ns_declared.push((self.stack.len(), prefix));
+			} else {
+				self.scope_default_namespace(!uri.is_empty(), !uri.is_empty());
+			}
 		}
 		Ok(())
 	}
@@ -161,60 +1113,464 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn elem(&mut self, name: &str) -> Result<(), Error> {
+		self.check_name(name)?;
+		self.record_leaf(name)?;
 		self.close_elem(false)?;
+		self.top_level_gap()?;
 		self.indent()?;
-		self.write(OPEN)?;
+		self.start_tag()?;
 		let ns = self.namespace;
 		self.ns_prefix(ns)?;
 		self.write(name)?;
-		self.write(CLOSE_CLOSE)
+		self.end_leaf(ns, name)?;
+		self.final_newline()
 	}
 
 	/// Write an element with inlined text content (escaped)
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn elem_text(&mut self, name: &str, text: &str) -> Result<(), Error> {
+		self.check_name(name)?;
+		self.record_in(self.namespace, name, Some(text))?;
 		self.close_elem(false)?;
+		self.top_level_gap()?;
 		self.indent()?;
-		self.write(OPEN)?;
+		self.start_tag()?;
 		let ns = self.namespace;
 		self.ns_prefix(ns)?;
 		self.write(name)?;
 		self.write(CLOSE)?;
 
-		self.escape(text, false)?;
+		self.escape(text, Escape::Text)?;
 
 		self.write(SELF_CLOSE_OPEN)?;
+		self.ns_prefix(ns)?;
 		self.write(name)?;
-		self.write(CLOSE)
+		self.write(CLOSE)?;
+		self.observe_close(name);
+		self.final_newline()
 	}
 
-	/// Begin an elem, make sure name contains only allowed chars
+	/// Write an element with inlined text content (escaped) within the namespace `prefix`,
+	/// like `<prefix:name>text</prefix:name>`.
 	/// # Errors
 	/// - if writing to buffer fails
-	pub fn begin_elem(&mut self, name: &'a str) -> Result<(), Error> {
-		self.close_elem(true)?;
-		// change previous elem to having children
-		if let Some(mut previous) = self.stack.pop() {
-			previous.1 = true;
-			self.stack.push(previous);
+	/// - if `prefix` is not a valid `NCName`, nothing is written in that case
+	pub fn elem_text_ns(&mut self, prefix: &'a str, name: &str, text: &str) -> Result<(), Error> {
+		if !is_ncname(prefix) {
+			return Err(Error::InvalidName);
 		}
+		self.check_name(name)?;
+		self.record_in(Some(prefix), name, Some(text))?;
+		self.open_child()?;
+		self.top_level_gap()?;
 		self.indent()?;
-		self.stack.push((name, false, self.namespace));
-		self.write(OPEN)?;
-		self.opened = true;
-		// stderr().write_fmt(format_args!("\nbegin {}", name));
-		let ns = self.namespace;
-		self.ns_prefix(ns)?;
-		self.write(name)
-	}
+		self.start_tag()?;
+		self.ns_prefix(Some(prefix))?;
+		self.write(name)?;
+		self.write(CLOSE)?;
 
-	/// Close an elem if open, do nothing otherwise.
-	/// # Errors
-	/// - if writing to buffer fails
+		self.escape(text, Escape::Text)?;
+
+		self.write(SELF_CLOSE_OPEN)?;
+		self.ns_prefix(Some(prefix))?;
+		self.write(name)?;
+		self.write(CLOSE)?;
+		self.observe_close(name);
+		self.final_newline()
+	}
+
+	/// Write an element with escaped attributes and inlined escaped text content,
+	/// like `<name a="1">text</name>`, also in `pretty` mode.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if a name is invalid and names are validated, nothing is written in that case
+	/// - if the attribute value sanitizer rejects a value or it exceeds the maximum length,
+	///   nothing is written in that case
+	pub fn elem_text_attrs<I>(&mut self, name: &'a str, attrs: I, text: &str) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'a str, &'a str)>,
+		I::IntoIter: Clone,
+	{
+		let attrs = attrs.into_iter();
+		self.check_name(name)?;
+		for (attr, value) in attrs.clone() {
+			self.check_name(attr)?;
+			self.check_attr_value(value)?;
+		}
+		self.begin_elem(name)?;
+		for (attr, value) in attrs {
+			self.attr_esc(attr, value)?;
+		}
+		self.text(text)?;
+		self.end_elem()
+	}
+
+	/// Write an element with the escaped text `value` like `<name>value</name>`, also in `pretty` mode.
+	/// If `value` is `None`, an empty element `<name/>` is written, or nothing if `omit_if_none` is `true`.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the name is invalid and names are validated
+	pub fn elem_text_opt(&mut self, name: &'a str, value: Option<&str>, omit_if_none: bool) -> Result<(), Error> {
+		match value {
+			Some(text) => self.elem_text_attrs(name, core::iter::empty(), text),
+			None if omit_if_none => Ok(()),
+			None => self.empty_elem(name),
+		}
+	}
+
+	/// Write an element representing a null value the XML Schema way, like `<name xsi:nil="true"/>`.
+	/// The `xsi` prefix has to be declared for `http://www.w3.org/2001/XMLSchema-instance`,
+	/// which is checked if namespaces are tracked, see [`XmlWriter::set_track_namespaces`].
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the name is invalid and names are validated
+	/// - [`Error::UndeclaredNamespace`] if `xsi` is not declared and namespaces are tracked,
+	///   nothing is written in that case
+	pub fn elem_nil(&mut self, name: &'a str) -> Result<(), Error> {
+		self.check_declared("xsi")?;
+		self.begin_elem(name)?;
+		self.attr_ns("xsi", "nil", "true")?;
+		self.end_elem()
+	}
+
+	/// Write an element with `data` encoded as standard base64 with padding as text content,
+	/// e.g. for certificates or signatures. The encoded text is written in chunks without an intermediate [`String`].
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the name is invalid and names are validated
+	pub fn elem_base64(&mut self, name: &'a str, data: &[u8]) -> Result<(), Error> {
+		self.begin_elem(name)?;
+		self.encoded_text(data, encode::base64)?;
+		self.end_elem()
+	}
+
+	/// Write an element with `data` encoded as lowercase hex as text content, e.g. for hashes.
+	/// The encoded text is written in chunks without an intermediate [`String`].
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the name is invalid and names are validated
+	pub fn elem_hex(&mut self, name: &'a str, data: &[u8]) -> Result<(), Error> {
+		self.begin_elem(name)?;
+		self.encoded_text(data, encode::hex)?;
+		self.end_elem()
+	}
+
+	/// Write `data` encoded by `encode` as text content, which needs no escaping
+	/// # Errors
+	/// - if writing to buffer fails
+	fn encoded_text(&mut self, data: &[u8], encode: encode::Encoder) -> Result<(), Error> {
+		if data.is_empty() {
+			return Ok(());
+		}
+		self.open_child()?;
+		self.newline = false;
+		let mut recorded = self.recording().then(String::new);
+		encode(data, &mut |chunk| {
+			if let Some(recorded) = recorded.as_mut() {
+				recorded.extend(chunk.iter().copied().map(char::from));
+			}
+			self.write_slice(chunk)
+		})?;
+		if let Some(text) = recorded {
+			self.record(|| Event::Text(text))?;
+		}
+		Ok(())
+	}
+
+	/// Write nested elements along the `/` separated `path` with the escaped `text` in the innermost one,
+	/// e.g. `config/server/port` results in `<config><server><port>text</port></server></config>`.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if a segment of `path` is not a valid name, nothing is written in that case
+	pub fn elem_path(&mut self, path: &'a str, text: &str) -> Result<(), Error> {
+		if !path.split('/').all(is_name) {
+			return Err(Error::InvalidName);
+		}
+		let mut depth = 0;
+		for segment in path.split('/') {
+			self.begin_elem(segment)?;
+			depth += 1;
+		}
+		self.text(text)?;
+		for _ in 0..depth {
+			self.end_elem()?;
+		}
+		Ok(())
+	}
+
+	/// Write flat config entries with `.` separated keys as nested elements with the escaped values as text,
+	/// e.g. `server.host = localhost` and `server.port = 8080` result in
+	/// `<server><host>localhost</host><port>8080</port></server>`.
+	/// The entries are sorted by their keys, so that elements with a shared key prefix are merged.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if a segment of a key is not a valid name, nothing is written in that case
+	pub fn nested_config<I: IntoIterator<Item = (&'a str, &'a str)>>(&mut self, entries: I) -> Result<(), Error> {
+		let mut entries: Vec<_> = entries.into_iter().collect();
+		if !entries
+			.iter()
+			.all(|(key, _)| key.split('.').all(is_name))
+		{
+			return Err(Error::InvalidName);
+		}
+		entries.sort_by(|(a, _), (b, _)| a.split('.').cmp(b.split('.')));
+		let mut open: Vec<&'a str> = Vec::new();
+		for (key, value) in entries {
+			let (parents, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+			let parents: Vec<_> = parents
+				.split('.')
+				.filter(|segment| !segment.is_empty())
+				.collect();
+			let shared = open
+				.iter()
+				.zip(&parents)
+				.take_while(|(open, parent)| open == parent)
+				.count();
+			while open.len() > shared {
+				self.end_elem()?;
+				open.pop();
+			}
+			for parent in &parents[shared..] {
+				self.begin_elem(parent)?;
+				open.push(parent);
+			}
+			self.begin_elem(leaf)?;
+			self.text(value)?;
+			self.end_elem()?;
+		}
+		for _ in open {
+			self.end_elem()?;
+		}
+		Ok(())
+	}
+
+	/// Begin an elem, make sure name contains only allowed chars.
+	///
+	/// The start tag is left open until the next output, so whether the elem is self-closing is decided
+	/// when it gets content or is closed: [`XmlWriter::end_elem`] without content in between results in
+	/// `<name a="1"/>`, unless [`XmlWriter::set_expand_empty_elements`] is enabled.
+	/// This is the pattern for elements which may or may not get children.
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn begin_elem(&mut self, name: &'a str) -> Result<(), Error> {
+		self.check_name(name)?;
+		self.begin_elem_in(self.namespace, name)
+	}
+
+	/// Begin an elem within `namespace`
+	/// # Errors
+	/// - if writing to buffer fails
+	fn begin_elem_in(&mut self, namespace: Option<&'a str>, name: &'a str) -> Result<(), Error> {
+		self.open_child()?;
+		self.elem_starts.push(self.elem_start());
+		self.record(|| Event::StartElem {
+			namespace,
+			name: Cow::Borrowed(name),
+		})?;
+		self.top_level_gap()?;
+		self.indent()?;
+		self.start_tag()?;
+		self.stack.push((name, false));
+		self.ns_stack.push(namespace);
+		self.opened = true;
+		self.ns_prefix(namespace)?;
+		self.write(name)?;
+		self.decorate(name)
+	}
+
+	/// Skip the indentation before the next elem, comment or closing tag in `pretty` mode once,
+	/// e.g. to keep a single child on the line of its parent. Subsequent content is indented again.
+	pub const fn suppress_next_indent(&mut self) {
+		self.skip_indent = true;
+	}
+
+	/// Begin an elem within the namespace `prefix` and declare it for `uri`,
+	/// like `<soap:Envelope xmlns:soap="uri">`.
+	/// The namespace is active for the children and the previous one is restored when the elem is closed.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `prefix` is not a valid `NCName` or reserved, see [`XmlWriter::ns_decl_iter`],
+	///   nothing is written in that case
+	/// - if the name is invalid and names are validated, nothing is written in that case
+	pub fn begin_elem_ns_decl(&mut self, prefix: &'a str, name: &'a str, uri: &'a str) -> Result<(), Error> {
+		if !is_ncname(prefix) {
+			return Err(Error::InvalidName);
+		}
+		if is_reserved_prefix(prefix, uri) {
+			return Err(Error::ReservedPrefix { prefix: prefix.into() });
+		}
+		self.check_name(name)?;
+		self.begin_elem_in(Some(prefix), name)?;
+		self.ns_decl_iter([(Some(prefix), uri)])?;
+		self.ns_scopes
+			.push((self.stack.len(), self.namespace));
+		self.namespace = Some(prefix);
+		Ok(())
+	}
+
+	/// Declare the namespaces `declarations` in the currently open element like [`XmlWriter::ns_decl_iter`]
+	/// and make `active` the namespace of the following elements, e.g. after `begin_elem("soap:Body")`.
+	/// The previous namespace is restored when the element is closed.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when opening a namespace without having an element
+	/// - if `active` or a prefix is not a valid `NCName` or reserved, see [`XmlWriter::ns_decl_iter`],
+	///   nothing is written in that case
+	pub fn enter_ns_scope(
+		&mut self,
+		declarations: &[(Option<&'a str>, &'a str)],
+		active: Option<&'a str>,
+	) -> Result<(), Error> {
+		if active.is_some_and(|prefix| !is_ncname(prefix)) {
+			return Err(Error::InvalidName);
+		}
+		self.ns_decl_iter(declarations.iter().copied())?;
+		// a scope already opened with the elem keeps the namespace to restore
+		if self
+			.ns_scopes
+			.last()
+			.is_none_or(|(depth, _)| *depth < self.stack.len())
+		{
+			self.ns_scopes
+				.push((self.stack.len(), self.namespace));
+		}
+		self.namespace = active;
+		Ok(())
+	}
+
+	/// Begin an elem like [`XmlWriter::begin_elem`], whose content is inline:
+	/// in `pretty` mode neither child elements nor text are indented until it is closed,
+	/// e.g. for mixed content like `<p>Hello <b>world</b>!</p>`.
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn inline_elem(&mut self, name: &'a str) -> Result<(), Error> {
+		self.begin_elem(name)?;
+		if self.preserve_depth.is_none() {
+			self.preserve_depth = Some(self.stack.len());
+		}
+		Ok(())
+	}
+
+	/// Begin an elem like [`XmlWriter::begin_elem`],
+	/// whose attributes are written in insertion order even if attributes are sorted,
+	/// see [`XmlWriter::set_sort_attributes`].
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn begin_elem_ordered(&mut self, name: &'a str) -> Result<(), Error> {
+		self.begin_elem(name)?;
+		self.ordered = true;
+		Ok(())
+	}
+
+	/// Close the current elem and begin its next sibling `name`,
+	/// e.g. for a flat list of records.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if there is no open elem, nothing is written in that case
+	pub fn next_sibling(&mut self, name: &'a str) -> Result<(), Error> {
+		if self.stack.is_empty() {
+			return Err(Error::CloseElement);
+		}
+		self.end_elem()?;
+		self.begin_elem(name)
+	}
+
+	/// Begin an elem like [`XmlWriter::begin_elem`],
+	/// returning a token to close it with [`XmlWriter::end_elem_checked`].
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn open(&mut self, name: &'a str) -> Result<OpenElem<'a>, Error> {
+		self.begin_elem(name)?;
+		Ok(OpenElem {
+			name,
+			depth: self.stack.len(),
+		})
+	}
+
+	/// End the elem of the given token, which must be the current elem.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the token does not belong to the current elem, nothing is closed in that case
+	// the token is consumed deliberately, so it can not be reused
+	#[allow(clippy::needless_pass_by_value)]
+	pub fn end_elem_checked(&mut self, token: OpenElem<'a>) -> Result<(), Error> {
+		match self.stack.last() {
+			Some((name, _)) if *name == token.name && self.stack.len() == token.depth => self.end_elem(),
+			_ => Err(Error::MismatchedClose),
+		}
+	}
+
+	/// Write the elem `name` only if the closure `f` writing its content returns `true`,
+	/// otherwise nothing is written at all. Elems left open by `f` are closed.
+	/// This uses [`XmlWriter::checkpoint`] and [`XmlWriter::rollback`],
+	/// so it is only supported for buffers which can be truncated.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - any error returned by `f`, what has been written is rolled back in that case as well
+	/// - if the buffer does not support truncation and `f` returns `false`
+	pub fn elem_if<F: FnOnce(&mut Self) -> Result<bool, Error>>(&mut self, name: &'a str, f: F) -> Result<(), Error> {
+		let checkpoint = self.checkpoint();
+		let written = self.begin_elem(name).and_then(|()| {
+			let depth = self.stack.len();
+			if !f(self)? {
+				return Ok(false);
+			}
+			while self.stack.len() >= depth {
+				self.end_elem()?;
+			}
+			Ok(true)
+		});
+		match written {
+			Ok(true) => Ok(()),
+			Ok(false) => self.rollback(checkpoint),
+			Err(error) => {
+				// a failed elem leaves nothing behind, the error of `f` is reported in any case
+				let _ = self.rollback(checkpoint);
+				Err(error)
+			}
+		}
+	}
+
+	/// Write each of `items` with the closure `f`, which may write any structure,
+	/// e.g. the differently shaped children of a list. In contrast to a wrapping helper
+	/// no elem is written around the items. Writing stops at the first error.
+	/// # Errors
+	/// - the first error returned by `f`
+	pub fn each<T, I, F>(&mut self, items: I, mut f: F) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = T>,
+		F: FnMut(&mut Self, T) -> Result<(), Error>,
+	{
+		items
+			.into_iter()
+			.try_for_each(|item| f(self, item))
+	}
+
+	/// Finish the tag of an element without children,
+	/// either self-closing or by an end tag if empty elements are expanded.
+	/// # Errors
+	/// - if writing to buffer fails
+	fn end_empty(&mut self, namespace: Option<&'a str>, name: &str) -> Result<(), Error> {
+		if self.config.expand_empty {
+			self.write(CLOSE)?;
+			self.write(SELF_CLOSE_OPEN)?;
+			self.ns_prefix(namespace)?;
+			self.write(name)?;
+			self.write(CLOSE)
+		} else {
+			self.write(CLOSE_CLOSE)
+		}
+	}
+
+	/// Close an elem if open, do nothing otherwise.
+	/// # Errors
+	/// - if writing to buffer fails
 	fn close_elem(&mut self, has_children: bool) -> Result<(), Error> {
 		if self.opened {
-			if has_children {
+			self.write_staged()?;
+			self.ordered = false;
+			if has_children || self.config.expand_empty {
 				self.write(CLOSE)?;
 			} else {
 				self.write(CLOSE_CLOSE)?;
@@ -224,160 +1580,1076 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 		Ok(())
 	}
 
+	/// Close the current elem if open and mark it as having children.
+	/// # Errors
+	/// - if writing to buffer fails
+	fn open_child(&mut self) -> Result<(), Error> {
+		self.close_elem(true)?;
+		if let Some(previous) = self.stack.last_mut() {
+			previous.1 = true;
+		}
+		Ok(())
+	}
+
 	/// End and elem
 	/// # Errors
 	/// - if writing to buffer fails
 	/// - when trying to close an element without having one opened
 	pub fn end_elem(&mut self) -> Result<(), Error> {
 		self.close_elem(false)?;
-		match self.stack.pop() {
-			Some((name, children, ns)) => {
-				// elem without children have been self-closed
-				if !children {
-					return Ok(());
+		match (self.stack.pop(), self.ns_stack.pop()) {
+			(Some((name, children)), Some(ns)) => {
+				self.check_stacks()?;
+				let start = self.elem_starts.pop();
+				// the staged attributes of the elem are not needed for a rollback anymore
+				let staged = self
+					.staged_spans
+					.iter()
+					.position(|(_, depth, ..)| *depth > self.stack.len())
+					.unwrap_or(self.staged_spans.len());
+				self.discard_staged(staged);
+				// leaving the scope of the namespaces declared with the elem
+				while self
+					.ns_declared
+					.last()
+					.is_some_and(|(depth, _)| *depth > self.stack.len())
+				{
+					self.ns_declared.pop();
 				}
-				if self.newline {
-					self.indent()?;
+				// leaving the scope of a namespace opened with the elem
+				if self
+					.ns_scopes
+					.last()
+					.is_some_and(|(depth, _)| *depth > self.stack.len())
+					&& let Some((_, namespace)) = self.ns_scopes.pop()
+				{
+					self.namespace = namespace;
 				}
-				self.newline = true;
-				self.write(SELF_CLOSE_OPEN)?;
-				self.ns_prefix(ns)?;
-				self.write(name)?;
-				self.write(CLOSE)?;
+				if self
+					.default_ns_scopes
+					.last()
+					.is_some_and(|(depth, ..)| *depth > self.stack.len())
+					&& let Some((_, active, declared)) = self.default_ns_scopes.pop()
+				{
+					self.default_namespace = active;
+					self.default_declared = declared;
+				}
+				self.record(|| Event::EndElem {
+					namespace: ns,
+					name: Cow::Borrowed(name),
+				})?;
+				// the content of a preserving elem ends without indentation
+				let preserved = self
+					.preserve_depth
+					.is_some_and(|depth| self.stack.len() < depth);
+				if preserved {
+					self.preserve_depth = None;
+				}
+				// elem without children have been self-closed, unless they are expanded
+				if children || self.config.expand_empty {
+					if children {
+						if self.newline && !preserved {
+							self.indent()?;
+						}
+						self.newline = true;
+					}
+					self.write(SELF_CLOSE_OPEN)?;
+					self.ns_prefix(ns)?;
+					self.write(name)?;
+					self.write(CLOSE)?;
+				}
+				self.final_newline()?;
+				self.last_elem = start.map(|start| (start, self.bytes_written));
+				self.observe_close(name);
 				Ok(())
 			}
-			None => Err(Error::CloseElement),
+			(None, None) => Err(Error::CloseElement),
+			_ => Err(Error::StackDesync),
 		}
 	}
 
+	/// Check that each open element has its namespace.
+	/// # Errors
+	/// - [`Error::StackDesync`] if the element and namespace stacks differ in length
+	fn check_stacks(&self) -> Result<(), Error> {
+		let synced = self.stack.len() == self.ns_stack.len();
+		debug_assert!(synced, "element and namespace stacks out of sync");
+		if synced { Ok(()) } else { Err(Error::StackDesync) }
+	}
+
 	/// Begin an empty elem
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn empty_elem(&mut self, name: &'a str) -> Result<(), Error> {
-		self.close_elem(true)?;
-		// change previous elem to having children
-		if let Some(mut previous) = self.stack.pop() {
-			previous.1 = true;
-			self.stack.push(previous);
-		}
+		self.check_name(name)?;
+		self.open_child()?;
+		let start = self.elem_start();
+		self.record_leaf(name)?;
+		self.top_level_gap()?;
 		self.indent()?;
-		self.write(OPEN)?;
+		self.start_tag()?;
 		let ns = self.namespace;
 		self.ns_prefix(ns)?;
 		self.write(name)?;
-		self.write(CLOSE_CLOSE)
+		self.end_leaf(ns, name)?;
+		self.final_newline()?;
+		self.last_elem = Some((start, self.bytes_written));
+		Ok(())
+	}
+
+	/// Write `count` empty elems like `<slot/>`, e.g. as placeholders in a table,
+	/// in `pretty` mode each on its own line.
+	/// # Errors
+	/// - if writing to buffer fails, no further elems are written in that case
+	/// - if the name is invalid and names are validated, nothing is written in that case
+	pub fn repeat_empty_elem(&mut self, name: &'a str, count: usize) -> Result<(), Error> {
+		(0..count).try_for_each(|_| self.empty_elem(name))
 	}
 
-	/// Write an attr, make sure name and value contain only allowed chars.
-	/// For an escaping version use `attr_esc`
+	/// Write an empty elem within the namespace `prefix`, or without a prefix if `None`,
+	/// with the escaped attributes `attrs`, like `<svg:use xlink:href="#x"/>`.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `prefix` is not a valid `NCName`, nothing is written in that case
+	/// - if a name is invalid and names are validated, nothing is written in that case
+	/// - if the attribute value sanitizer rejects a value or it exceeds the maximum length,
+	///   nothing is written in that case
+	pub fn empty_elem_ns_attrs(
+		&mut self,
+		prefix: Option<&'a str>,
+		name: &'a str,
+		attrs: &[(&str, &str)],
+	) -> Result<(), Error> {
+		if prefix.is_some_and(|prefix| !is_ncname(prefix)) {
+			return Err(Error::InvalidName);
+		}
+		self.check_name(name)?;
+		for (attr, value) in attrs {
+			self.check_name(attr)?;
+			self.check_attr_value(value)?;
+		}
+		self.begin_elem_in(prefix, name)?;
+		for (attr, value) in attrs {
+			self.attr_esc(attr, value)?;
+		}
+		self.end_elem()
+	}
+
+	/// Write an attr with a value known to contain only allowed chars, it is written as is.
+	/// For an escaping version use `attr_esc`, for an already escaped value use `attr_raw`.
 	/// # Errors
 	/// - if writing to buffer fails
 	/// - when writing attributes without having an element
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
 	pub fn attr(&mut self, name: &str, value: &str) -> Result<(), Error> {
 		if !self.opened {
 			return Err(Error::WriteWithoutElement);
 		}
-		self.write(SPACE)?;
-		self.write(name)?;
-		self.write(EQUAL_QUOTE)?;
-		self.write(value)?;
-		self.write(QUOTE)
+		self.check_name(name)?;
+		self.check_attr_value(value)?;
+		self.record(|| Event::Attr {
+			name: name.into(),
+			value: value.into(),
+		})?;
+		self.staged_attr(false, &[name], |xml| {
+			xml.write(SPACE)?;
+			xml.write(name)?;
+			xml.write(xml.config.quote.open())?;
+			xml.write(value)?;
+			xml.write(xml.config.quote.close())
+		})
+	}
+
+	/// Write an attr with an already escaped value, e.g. cached or produced elsewhere, like `a="x &amp; y"`.
+	/// The value is written verbatim within the quotes and recorded as given, so it must not contain
+	/// unescaped `<`, `&` or the quote char in use.
+	/// For a value with only allowed chars use `attr`, for an unescaped value use `attr_esc`.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
+	pub fn attr_raw(&mut self, name: &str, value: &str) -> Result<(), Error> {
+		// both write the value verbatim, they differ only in what the caller guarantees
+		self.attr(name, value)
+	}
+
+	/// Write an attr without value like the boolean attribute `disabled` in HTML.
+	/// This is an escape hatch for HTML compatible output, the result is not well-formed XML.
+	/// It is recorded as an attribute with an empty value.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	pub fn attr_bare(&mut self, name: &str) -> Result<(), Error> {
+		if !self.opened {
+			return Err(Error::WriteWithoutElement);
+		}
+		self.check_name(name)?;
+		self.record(|| Event::Attr {
+			name: name.into(),
+			value: String::new(),
+		})?;
+		self.staged_attr(false, &[name], |xml| {
+			xml.write(SPACE)?;
+			xml.write(name)
+		})
 	}
 
 	/// Write an attr, make sure name contains only allowed chars.
 	/// # Errors
 	/// - if writing to buffer fails
 	/// - when writing attributes without having an element
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
 	pub fn attr_esc(&mut self, name: &str, value: &str) -> Result<(), Error> {
 		if !self.opened {
 			return Err(Error::WriteWithoutElement);
 		}
-		self.write(SPACE)?;
-		self.escape(name, true)?;
-		self.write(EQUAL_QUOTE)?;
-		self.escape(value, false)?;
-		self.write(QUOTE)
+		self.check_name(name)?;
+		self.check_attr_value(value)?;
+		self.record(|| Event::Attr {
+			name: name.into(),
+			value: value.into(),
+		})?;
+		self.staged_attr(false, &[name], |xml| {
+			xml.write(SPACE)?;
+			xml.escape(name, Escape::Name)?;
+			xml.write(xml.config.quote.open())?;
+			xml.escape(value, Escape::Value)?;
+			xml.write(xml.config.quote.close())
+		})
+	}
+
+	/// Write an attr with an escaped value streamed in chunks,
+	/// e.g. for large values which shall not be held in memory as a whole.
+	/// `chunk_reader` fills the given buffer and returns the number of bytes written into it,
+	/// returning `0` ends the value. The chunks must form valid UTF-8 as a whole.
+	/// The value is escaped like in [`XmlWriter::attr_esc`], also across chunk boundaries.
+	/// The attribute value sanitizer is not applied.
+	///
+	/// If `chunk_reader` fails or the value exceeds the maximum length, the chunks read before are written
+	/// as a terminated value, so that the output stays well-formed, but the chunk exceeding the maximum is not.
+	/// If attributes are staged, see [`XmlWriter::set_sort_attributes`], the attribute is discarded instead.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	/// - any error returned by `chunk_reader`
+	/// - if the value exceeds the maximum length, see [`XmlWriter::set_max_attr_value_len`]
+	pub fn attr_streamed<F: FnMut(&mut [u8]) -> Result<usize, Error>>(
+		&mut self,
+		name: &str,
+		mut chunk_reader: F,
+	) -> Result<(), Error> {
+		if !self.opened {
+			return Err(Error::WriteWithoutElement);
+		}
+		self.check_name(name)?;
+		self.staged_attr(false, &[name], |xml| {
+			xml.write(SPACE)?;
+			xml.escape(name, Escape::Name)?;
+			xml.write(xml.config.quote.open())?;
+			let mut recorded = xml.recording().then(Vec::new);
+			let mut chunk = [0u8; 256];
+			// a normalized `\r` at the end of a chunk, which may be followed by a `\n`
+			let mut pending_cr = false;
+			let mut total = 0;
+			let result = loop {
+				let len = match chunk_reader(&mut chunk) {
+					Ok(len) => len.min(chunk.len()),
+					Err(err) => break Err(err),
+				};
+				if len == 0 {
+					break Ok(());
+				}
+				total += len;
+				if let Err(err) = xml.check_attr_value_len(total) {
+					break Err(err);
+				}
+				let mut bytes = &chunk[..len];
+				if let Some(recorded) = recorded.as_mut() {
+					recorded.extend_from_slice(bytes);
+				}
+				if pending_cr {
+					pending_cr = false;
+					if bytes.first() != Some(&b'\n') {
+						xml.write("&#10;")?;
+					}
+				}
+				if xml.config.normalize_newlines
+					&& !xml.config.canonical
+					&& let Some(rest) = bytes.strip_suffix(b"\r")
+				{
+					pending_cr = true;
+					bytes = rest;
+				}
+				xml.escape_bytes(bytes, Escape::Value)?;
+			};
+			if pending_cr {
+				xml.write("&#10;")?;
+			}
+			// the value written so far is terminated, so that the output stays well-formed
+			xml.write(xml.config.quote.close())?;
+			result?;
+			if let Some(recorded) = recorded {
+				xml.record(|| Event::Attr {
+					name: name.into(),
+					value: String::from_utf8_lossy(&recorded).into_owned(),
+				})?;
+			}
+			Ok(())
+		})
+	}
+
+	/// Write a namespaced attr `prefix:name="value"`, the value is escaped.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	/// - if `prefix` or `name` is not a valid `NCName`
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
+	pub fn attr_ns(&mut self, prefix: &str, name: &str, value: &str) -> Result<(), Error> {
+		if !self.opened {
+			return Err(Error::WriteWithoutElement);
+		}
+		if !is_ncname(prefix) || !is_ncname(name) {
+			return Err(Error::InvalidName);
+		}
+		self.check_reserved(name)?;
+		self.check_attr_value(value)?;
+		self.record(|| Event::Attr {
+			name: [prefix, ":", name].concat(),
+			value: value.into(),
+		})?;
+		self.staged_attr(false, &[prefix, ":", name], |xml| {
+			xml.write(SPACE)?;
+			xml.write(prefix)?;
+			xml.write(":")?;
+			xml.write(name)?;
+			xml.write(xml.config.quote.open())?;
+			xml.escape(value, Escape::Value)?;
+			xml.write(xml.config.quote.close())
+		})
+	}
+
+	/// Register `prefix` for the namespace `uri`, so that attributes can be written by the URI,
+	/// see [`XmlWriter::attr_ns_uri`]. A URI registered again gets the new prefix.
+	/// The namespace still has to be declared, e.g. with [`XmlWriter::ns_decl`].
+	/// # Errors
+	/// - if `prefix` is not a valid `NCName`
+	/// - if `prefix` is `xmlns` or `xml` is registered for another than its standard URI
+	pub fn register_ns(&mut self, prefix: &'a str, uri: &'a str) -> Result<(), Error> {
+		if !is_ncname(prefix) {
+			return Err(Error::InvalidName);
+		}
+		if is_reserved_prefix(prefix, uri) {
+			return Err(Error::ReservedPrefix { prefix: prefix.into() });
+		}
+		self.ns_registry
+			.retain(|(_, registered)| *registered != uri);
+		self.ns_registry.push((prefix, uri));
+		Ok(())
+	}
+
+	/// Write a namespaced attr `prefix:name="value"` with the prefix registered for the namespace `uri`,
+	/// see [`XmlWriter::register_ns`], the value is escaped.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	/// - [`Error::UndeclaredNamespace`] if no prefix is registered for `uri`,
+	///   or the prefix is not declared and namespaces are tracked, see [`XmlWriter::set_track_namespaces`]
+	/// - if `name` is not a valid `NCName`
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
+	pub fn attr_ns_uri(&mut self, uri: &str, name: &str, value: &str) -> Result<(), Error> {
+		let prefix = self
+			.ns_registry
+			.iter()
+			.find(|(_, registered)| *registered == uri)
+			.map(|(prefix, _)| *prefix)
+			.ok_or_else(|| Error::UndeclaredNamespace { namespace: uri.into() })?;
+		self.check_declared(prefix)?;
+		self.attr_ns(prefix, name, value)
+	}
+
+	/// Write an attr, escaping only `&`, `<` and the delimiting quote, see [`Quote`], in the value.
+	/// `>` and the other quote are well-formed within attribute values and are written as is,
+	/// which keeps values containing many of them readable.
+	/// Note that `<` must be escaped within attribute values, too.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when writing attributes without having an element
+	/// - if the attribute value sanitizer rejects the value or it exceeds the maximum length
+	pub fn attr_min_esc(&mut self, name: &str, value: &str) -> Result<(), Error> {
+		if !self.opened {
+			return Err(Error::WriteWithoutElement);
+		}
+		self.check_name(name)?;
+		self.check_attr_value(value)?;
+		self.record(|| Event::Attr {
+			name: name.into(),
+			value: value.into(),
+		})?;
+		self.staged_attr(false, &[name], |xml| {
+			xml.write(SPACE)?;
+			xml.escape(name, Escape::Name)?;
+			xml.write(xml.config.quote.open())?;
+			xml.escape(value, Escape::Minimal)?;
+			xml.write(xml.config.quote.close())
+		})
+	}
+
+	/// Escape names, attribute values or text.
+	/// # Errors
+	/// - if writing to buffer fails
+	fn escape(&mut self, text: &str, mode: Escape) -> Result<(), Error> {
+		self.escape_bytes(text.as_bytes(), mode)
 	}
 
-	/// Escape identifiers or text.
+	/// Escape UTF-8 encoded bytes.
+	/// All escaped characters are ASCII, so unescaped runs are written in one go.
 	/// # Errors
 	/// - if writing to buffer fails
-	fn escape(&mut self, text: &str, ident: bool) -> Result<(), Error> {
-		for c in text.chars() {
-			match c {
-				'"' => self.write("&quot;")?,
-				'\'' => self.write("&apos;")?,
-				'&' => self.write("&amp;")?,
-				'<' => self.write("&lt;")?,
-				'>' => self.write("&gt;")?,
-				'\\' if ident => self.write("\\\\")?,
-				_ => self.write_slice(c.encode_utf8(&mut [0; 4]).as_bytes())?,
+	fn escape_bytes(&mut self, bytes: &[u8], mode: Escape) -> Result<(), Error> {
+		let normalize = self.config.normalize_newlines;
+		let canonical = self.config.canonical;
+		let single = self.config.quote == Quote::Single;
+		let literal_apos = self.config.apostrophe == ApostropheStyle::None;
+		let apos = self.config.apostrophe.escaped();
+		let strict = self.config.strict_escaping && !canonical;
+		let mode = if strict && matches!(mode, Escape::Value) {
+			Escape::Minimal
+		} else {
+			mode
+		};
+		let mut start = 0;
+		for (index, byte) in bytes.iter().enumerate() {
+			let crlf = *byte == b'\r' && bytes.get(index + 1) == Some(&b'\n');
+			let entity = match (byte, mode) {
+				(b'&', Escape::Verbatim) => "&amp;",
+				(b'<', Escape::Verbatim) => "&lt;",
+				(b'>', Escape::Verbatim) if index >= 2 && bytes[index - 2..index] == *b"]]" => "&gt;",
+				(_, Escape::Verbatim) => continue,
+				(b'"' | b'\'', Escape::Text) if strict => continue,
+				// a preceding `]` might be part of a `]]>`, also across writes
+				(b'>', Escape::Text) if strict && index > 0 && bytes[index - 1] != b']' => continue,
+				(b'"' | b'\'', Escape::Text) | (b'>', Escape::Value) if canonical => continue,
+				(b'\'', Escape::Value) if canonical && !single => continue,
+				(b'"', Escape::Minimal) if single => continue,
+				(b'\'', Escape::Text) if literal_apos => continue,
+				(b'\'', Escape::Name | Escape::Value) if literal_apos && !single => continue,
+				(b'\'', Escape::Minimal) if single => apos,
+				(b'\t', Escape::Value) if canonical => "&#x9;",
+				(b'\n', Escape::Value) if canonical => "&#xA;",
+				(b'\r', Escape::Text | Escape::Value) if canonical => "&#xD;",
+				(b'&', _) => "&amp;",
+				(b'"', _) => "&quot;",
+				(b'\'', Escape::Name | Escape::Text | Escape::Value) => apos,
+				(b'<', _) => "&lt;",
+				(b'>', Escape::Name | Escape::Text | Escape::Value) => "&gt;",
+				(b'\\', Escape::Name) => "\\\\",
+				// the `\n` of a `\r\n` is written on its own
+				(b'\r', Escape::Text | Escape::Value | Escape::Minimal) if normalize && crlf => "",
+				(b'\r', Escape::Text) if normalize => "\n",
+				(b'\r' | b'\n', Escape::Value | Escape::Minimal) if normalize => "&#10;",
+				_ => continue,
+			};
+			if start < index {
+				self.write_slice(&bytes[start..index])?;
 			}
+			self.write(entity)?;
+			start = index + 1;
+		}
+		if start < bytes.len() {
+			self.write_slice(&bytes[start..])?;
 		}
 		Ok(())
 	}
 
-	/// Write a text content, escapes the text automatically
+	/// Enable/disable ignoring empty text content in [`XmlWriter::text`], which is enabled by default.
+	/// If enabled, `text("")` does nothing, so an element without other content is still self-closing.
+	/// If disabled, the start tag is closed, e.g. resulting in `<x></x>`.
+	pub const fn set_empty_text_is_noop(&mut self, enabled: bool) {
+		self.config.empty_text_is_noop = enabled;
+	}
+
+	/// Set a separator written between consecutive text content of an element, e.g. a space
+	/// to keep word boundaries for text extraction tools. The default is no separator.
+	/// It applies to [`XmlWriter::text`], [`XmlWriter::text_fmt`] and [`XmlWriter::text_with`]
+	/// and is escaped like text content.
+	pub const fn set_text_separator(&mut self, separator: &'static str) {
+		self.config.text_separator = separator;
+	}
+
+	/// Write the text separator, if the last output has been text content
+	/// # Errors
+	/// - if writing to buffer fails
+	fn text_separator(&mut self) -> Result<(), Error> {
+		if self.text_end == Some(self.bytes_written) {
+			self.escape(self.config.text_separator, Escape::Text)?;
+		}
+		Ok(())
+	}
+
+	/// Write a text content, escapes the text automatically.
+	/// Empty text is ignored by default, see [`XmlWriter::set_empty_text_is_noop`].
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn text(&mut self, text: &str) -> Result<(), Error> {
-		self.close_elem(true)?;
-		// change previous elem to having children
-		if let Some(mut previous) = self.stack.pop() {
-			previous.1 = true;
-			self.stack.push(previous);
+		if text.is_empty() && self.config.empty_text_is_noop {
+			return Ok(());
 		}
+		self.record(|| Event::Text(text.into()))?;
+		self.open_child()?;
 		self.newline = false;
-		self.escape(text, false)
+		self.text_separator()?;
+		self.escape(text, Escape::Text)?;
+		self.text_end = Some(self.bytes_written);
+		Ok(())
+	}
+
+	/// Write text content verbatim, e.g. source code or ASCII art.
+	/// Only `&`, `<` and a `>` following `]]` are escaped,
+	/// and the content of the current elem is not indented in `pretty` mode until it is closed.
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn verbatim(&mut self, text: &str) -> Result<(), Error> {
+		self.record(|| Event::Text(text.into()))?;
+		self.open_child()?;
+		if self.preserve_depth.is_none() && !self.stack.is_empty() {
+			self.preserve_depth = Some(self.stack.len());
+		}
+		self.newline = false;
+		self.escape(text, Escape::Verbatim)
+	}
+
+	/// Write an entity reference `&name;` as text content, e.g. `&nbsp;` for an entity declared in the DTD.
+	/// In contrast to [`XmlWriter::text`] the `&` is not escaped.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `name` is not a valid `NCName`, nothing is written in that case
+	pub fn raw_entity(&mut self, name: &str) -> Result<(), Error> {
+		if !is_ncname(name) {
+			return Err(Error::InvalidName);
+		}
+		self.record(|| Event::EntityRef(name.into()))?;
+		self.open_child()?;
+		self.newline = false;
+		self.write("&")?;
+		self.write(name)?;
+		self.write(";")
+	}
+
+	/// Write a decimal character reference `&#NNN;` as text content, e.g. `&#160;` for a non-breaking space.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `codepoint` is not a legal XML character, nothing is written in that case
+	pub fn char_ref(&mut self, codepoint: u32) -> Result<(), Error> {
+		self.write_char_ref(codepoint, 10)
+	}
+
+	/// Write a hexadecimal character reference `&#xHHH;` as text content, e.g. `&#xA0;` for a non-breaking space.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `codepoint` is not a legal XML character, nothing is written in that case
+	pub fn char_ref_hex(&mut self, codepoint: u32) -> Result<(), Error> {
+		self.write_char_ref(codepoint, 16)
+	}
+
+	/// Write the character reference for `codepoint` as text content, decimal or hexadecimal by `radix`
+	fn write_char_ref(&mut self, codepoint: u32, radix: u32) -> Result<(), Error> {
+		let Some(char) = char::from_u32(codepoint).filter(|_| is_xml_char(codepoint)) else {
+			return Err(Error::InvalidCharacter);
+		};
+		self.record(|| Event::Text(char.into()))?;
+		self.open_child()?;
+		self.newline = false;
+		// formatted backwards into a buffer large enough for `&#1114111;` and `&#x10FFFF;`
+		let mut reference = [b';'; 11];
+		let mut start = reference.len() - 1;
+		let mut rest = codepoint;
+		loop {
+			start -= 1;
+			reference[start] = b"0123456789ABCDEF"[(rest % radix) as usize];
+			rest /= radix;
+			if rest == 0 {
+				break;
+			}
+		}
+		if radix == 16 {
+			start -= 1;
+			reference[start] = b'x';
+		}
+		start -= 2;
+		reference[start..start + 2].copy_from_slice(b"&#");
+		self.write_slice(&reference[start..])
+	}
+
+	/// Write formatted text content without an intermediate [`String`],
+	/// e.g. `xml.text_fmt(format_args!("value: {n}"))`.
+	/// The formatted output is escaped like in [`XmlWriter::text`].
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if formatting fails
+	pub fn text_fmt(&mut self, args: core::fmt::Arguments) -> Result<(), Error> {
+		self.record(|| Event::Text(alloc::fmt::format(args)))?;
+		self.open_child()?;
+		self.newline = false;
+		self.text_separator()?;
+		let mut escaper = TextEscaper {
+			writer: self,
+			error: None,
+			pending_cr: false,
+		};
+		if core::fmt::write(&mut escaper, args).is_err() {
+			return Err(escaper.error.unwrap_or(Error::Fmt));
+		}
+		if escaper.pending_cr {
+			self.write("\n")?;
+		}
+		self.text_end = Some(self.bytes_written);
+		Ok(())
+	}
+
+	/// Write text content in pieces pushed by `f`, without concatenating them to a [`String`].
+	/// Each piece is escaped like in [`XmlWriter::text`], also a `\r\n` split across pieces.
+	/// ```
+	/// use woxml::XmlWriter;
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.begin_elem("sum")?;
+	/// xml.text_with(|push| {
+	///     push("1 < 2")?;
+	///     push(" & ")?;
+	///     push("2 > 1")
+	/// })?;
+	/// xml.end_elem()?;
+	/// assert_eq!(String::try_from(xml)?, "<sum>1 &lt; 2 &amp; 2 &gt; 1</sum>");
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - any error returned by `f`
+	pub fn text_with<F>(&mut self, f: F) -> Result<(), Error>
+	where
+		F: FnOnce(&mut dyn FnMut(&str) -> Result<(), Error>) -> Result<(), Error>,
+	{
+		self.open_child()?;
+		self.newline = false;
+		self.text_separator()?;
+		let mut pending_cr = false;
+		f(&mut |piece: &str| {
+			self.record(|| Event::Text(piece.into()))?;
+			self.escape_text_piece(piece, &mut pending_cr)
+		})?;
+		if pending_cr {
+			self.write("\n")?;
+		}
+		self.text_end = Some(self.bytes_written);
+		Ok(())
+	}
+
+	/// Escape a piece of text content, a normalized `\r` at its end is left `pending_cr`,
+	/// as it may be followed by the `\n` of a `\r\n` at the start of the next piece.
+	/// # Errors
+	/// - if writing to buffer fails
+	fn escape_text_piece(&mut self, piece: &str, pending_cr: &mut bool) -> Result<(), Error> {
+		let mut bytes = piece.as_bytes();
+		if bytes.is_empty() {
+			return Ok(());
+		}
+		if *pending_cr {
+			*pending_cr = false;
+			if bytes.first() != Some(&b'\n') {
+				self.write("\n")?;
+			}
+		}
+		if self.config.normalize_newlines
+			&& !self.config.canonical
+			&& let Some(rest) = bytes.strip_suffix(b"\r")
+		{
+			*pending_cr = true;
+			bytes = rest;
+		}
+		self.escape_bytes(bytes, Escape::Text)
 	}
 
 	/// Raw write, no escaping, no safety net, use at own risk
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn write(&mut self, text: &str) -> Result<(), Error> {
-		self.buffer.write_all(text.as_bytes())?;
-		Ok(())
+		self.write_slice(text.as_bytes())
 	}
 
 	/// Raw write, no escaping, no safety net, use at own risk
 	/// # Errors
 	/// - if writing to buffer fails
 	fn write_slice(&mut self, slice: &[u8]) -> Result<(), Error> {
-		self.buffer.write_all(slice)?;
+		if self.staging {
+			self.staged.extend_from_slice(slice);
+			return Ok(());
+		}
+		if self.truncated {
+			return Ok(());
+		}
+		// unlimited, the default
+		if self.config.max_output_bytes == usize::MAX {
+			return self.emit(slice);
+		}
+		let limit = self
+			.config
+			.max_output_bytes
+			.saturating_sub(self.config.truncation_headroom);
+		if self.bytes_written.saturating_add(slice.len()) > limit {
+			self.truncated = true;
+			if self.config.truncation_headroom > 0 {
+				return self.close_truncated();
+			}
+			return Ok(());
+		}
+		self.emit(slice)
+	}
+
+	/// Hand a slice to the observer and the pending output or the buffer
+	/// # Errors
+	/// - if writing to buffer fails
+	fn emit(&mut self, slice: &[u8]) -> Result<(), Error> {
+		if let Some(observer) = self.config.write_observer {
+			observer(slice);
+		}
+		let size = self.config.buffer_size;
+		// unbuffered, the default
+		if size == 0 && self.pending.is_empty() {
+			return self.write_through(slice);
+		}
+		if !self.pending.is_empty() && self.pending.len() + slice.len() > size {
+			self.write_pending()?;
+		}
+		if slice.len() >= size {
+			return self.write_through(slice);
+		}
+		self.pending.extend_from_slice(slice);
+		self.bytes_written += slice.len();
+		Ok(())
+	}
+
+	/// Hand a slice to the buffer, bypassing the pending output
+	/// # Errors
+	/// - if writing to buffer fails
+	fn write_through(&mut self, slice: &[u8]) -> Result<(), Error> {
+		let len = self.buffer.len();
+		if let Err(error) = self.buffer.write_all(slice) {
+			// account for a partial write, so that a rollback removes it
+			self.bytes_written += self.partially_written(len);
+			return Err(error);
+		}
+		self.bytes_written += slice.len();
+		Ok(())
+	}
+
+	/// Hand the pending output to the buffer
+	/// # Errors
+	/// - if writing to buffer fails
+	fn write_pending(&mut self) -> Result<(), Error> {
+		if !self.pending.is_empty() {
+			let len = self.buffer.len();
+			if let Err(error) = self.buffer.write_all(&self.pending) {
+				// pending output is already accounted for, keep only what has not been written
+				let written = self.partially_written(len);
+				self.pending.drain(..written);
+				return Err(error);
+			}
+			self.pending.clear();
+		}
 		Ok(())
 	}
 
+	/// The number of bytes written into the buffer since it had the length `len`,
+	/// `0` if the buffer is not able to tell its length
+	fn partially_written(&self, len: Option<usize>) -> usize {
+		len.zip(self.buffer.len())
+			.map_or(0, |(before, after)| after.saturating_sub(before))
+	}
+
+	/// Write the output of another writer as children of the current elem,
+	/// e.g. to compose a document from sections written in parallel.
+	/// In `pretty` mode each line of `child_bytes` is indented to the current depth and ended
+	/// with the configured line ending, so it should not contain multi-line text content.
+	/// `child_bytes` are written as is and must be a well-formed fragment.
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn append_child_document(&mut self, child_bytes: &[u8]) -> Result<(), Error> {
+		self.open_child()?;
+		if !self.config.pretty {
+			self.indent()?;
+			return self.write_slice(child_bytes);
+		}
+		let child_bytes = child_bytes
+			.strip_suffix(b"\n")
+			.unwrap_or(child_bytes);
+		for (index, line) in child_bytes
+			.split(|byte| *byte == b'\n')
+			.enumerate()
+		{
+			// the line ending of the child may differ from the one in use
+			let line = line.strip_suffix(b"\r").unwrap_or(line);
+			if index == 0 {
+				self.indent()?;
+			} else {
+				self.write(self.config.line_ending)?;
+				for _ in 0..self.stack.len() {
+					self.write(self.config.indent)?;
+				}
+			}
+			self.write_slice(line)?;
+		}
+		self.newline = true;
+		Ok(())
+	}
+
+	/// Set the handling of CDATA content containing the end marker `]]>`,
+	/// default is [`CdataPolicy::Split`].
+	pub const fn set_cdata_unsafe_policy(&mut self, policy: CdataPolicy) {
+		self.config.cdata_policy = policy;
+	}
+
+	/// Check CDATA content against the [`CdataPolicy`], returns `true` if it has to be escaped as text
+	/// # Errors
+	/// - [`Error::InvalidCdata`] if the content contains `]]>` and the policy is [`CdataPolicy::Error`]
+	fn check_cdata(&self, bytes: &[u8]) -> Result<bool, Error> {
+		let policy = self.config.cdata_policy;
+		if policy == CdataPolicy::Split || !bytes.windows(3).any(|window| window == b"]]>") {
+			return Ok(self.config.canonical);
+		}
+		match policy {
+			CdataPolicy::Error => Err(Error::InvalidCdata),
+			_ => Ok(true),
+		}
+	}
+
 	/// Write a CDATA.
+	/// An occurrence of `]]>` is handled according to the [`CdataPolicy`].
 	/// # Errors
 	/// - if writing to buffer fails
+	/// - [`Error::InvalidCdata`] if rejected by the [`CdataPolicy`], nothing is written in that case
 	pub fn cdata(&mut self, cdata: &str) -> Result<(), Error> {
-		self.close_elem(true)?;
-		// change previous elem to having children
-		if let Some(mut previous) = self.stack.pop() {
-			previous.1 = true;
-			self.stack.push(previous);
+		self.cdata_bytes(cdata.as_bytes())
+	}
+
+	/// Write a CDATA from bytes which are known to be UTF-8, without validating them.
+	/// An occurrence of `]]>` is handled according to the [`CdataPolicy`].
+	/// The caller is responsible for the bytes being valid UTF-8 encoded XML characters.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::InvalidCdata`] if rejected by the [`CdataPolicy`], nothing is written in that case
+	pub fn cdata_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+		let as_text = self.check_cdata(bytes)?;
+		self.record(|| Event::Cdata(String::from_utf8_lossy(bytes).into_owned()))?;
+		self.open_child()?;
+		if as_text {
+			self.newline = false;
+			return self.escape_bytes(bytes, Escape::Text);
 		}
-		if self.pretty {
+		if self.config.pretty || self.config.compact_spacing {
 			self.indent()?;
 		}
+		self.cdata_section(bytes)
+	}
+
+	/// Write a CDATA section, splitting it at occurrences of `]]>`
+	/// # Errors
+	/// - if writing to buffer fails
+	fn cdata_section(&mut self, bytes: &[u8]) -> Result<(), Error> {
 		self.write("<![CDATA[")?;
-		self.write(cdata)?;
+		let mut rest = bytes;
+		while let Some(index) = rest
+			.windows(3)
+			.position(|window| window == b"]]>")
+		{
+			self.write_slice(&rest[..index + 2])?;
+			self.write("]]><![CDATA[")?;
+			rest = &rest[index + 2..];
+		}
+		self.write_slice(rest)?;
 		self.write("]]>")
 	}
 
+	/// Write a CDATA without indentation in `pretty` mode, like text content.
+	/// Useful if the CDATA is the only content of an element: `<script><![CDATA[...]]></script>`.
+	/// An occurrence of `]]>` is handled according to the [`CdataPolicy`].
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::InvalidCdata`] if rejected by the [`CdataPolicy`], nothing is written in that case
+	pub fn cdata_inline(&mut self, cdata: &str) -> Result<(), Error> {
+		let as_text = self.check_cdata(cdata.as_bytes())?;
+		self.record(|| Event::Cdata(cdata.into()))?;
+		self.open_child()?;
+		self.newline = false;
+		if as_text {
+			return self.escape(cdata, Escape::Text);
+		}
+		self.cdata_section(cdata.as_bytes())
+	}
+
 	/// Write a comment
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn comment(&mut self, comment: &str) -> Result<(), Error> {
-		self.close_elem(true)?;
-		// change previous elem to having children
-		if let Some(mut previous) = self.stack.pop() {
-			previous.1 = true;
-			self.stack.push(previous);
+		self.record(|| Event::Comment(comment.into()))?;
+		self.open_child()?;
+		self.indent()?;
+		self.write("<!-- ")?;
+		self.escape(comment, Escape::Text)?;
+		self.write(" -->")
+	}
+
+	/// Write a comment as is, e.g. one read from existing XML, where escaping would change its content.
+	/// In contrast to [`XmlWriter::marker_comment`] it may span several lines.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::InvalidComment`] if the comment contains `--`, nothing is written in that case
+	pub fn comment_raw(&mut self, comment: &str) -> Result<(), Error> {
+		if comment.contains("--") {
+			return Err(Error::InvalidComment);
 		}
+		self.record(|| Event::Comment(comment.into()))?;
+		self.open_child()?;
 		self.indent()?;
 		self.write("<!-- ")?;
-		self.escape(comment, false)?;
+		self.write(comment)?;
+		self.write(" -->")
+	}
+
+	/// Write a processing instruction `<?target data?>`, or `<?target?>` if `data` is empty.
+	/// It may be written in the prolog as well as between elements.
+	/// Within an open elem the start tag is closed and the PI is written as its indented child.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `target` is not a valid `NCName` or a reserved `xml` target, nothing is written in that case
+	/// - if `data` contains `?>`, nothing is written in that case
+	pub fn pi(&mut self, target: &str, data: &str) -> Result<(), Error> {
+		if !is_ncname(target) || target.eq_ignore_ascii_case("xml") {
+			return Err(Error::InvalidName);
+		}
+		if data.contains("?>") {
+			return Err(Error::InvalidProcessingInstruction);
+		}
+		self.record(|| Event::Pi {
+			target: target.into(),
+			data: data.into(),
+		})?;
+		self.open_child()?;
+		self.indent()?;
+		self.write("<?")?;
+		self.write(target)?;
+		if !data.is_empty() {
+			self.write(SPACE)?;
+			self.write(data)?;
+		}
+		self.write("?>")
+	}
+
+	/// Write the processing instruction `<?xml-stylesheet type="text/xsl" href="style.xsl"?>` in the prolog,
+	/// linking a stylesheet of the MIME type `type_` at `href`.
+	/// The pseudo-attribute values are quoted with the configured [`Quote`],
+	/// `&`, `<` and the delimiting quote are escaped.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::ContentAlreadyWritten`] if an element has been written before, nothing is written in that case
+	/// - if a value contains `?>`, nothing is written in that case
+	pub fn stylesheet_pi(&mut self, type_: &str, href: &str) -> Result<(), Error> {
+		if self.has_root {
+			return Err(Error::ContentAlreadyWritten);
+		}
+		self.pi_attrs("xml-stylesheet", [("type", type_), ("href", href)])
+	}
+
+	/// Write a processing instruction with pseudo-attributes like `<?target key1="v1" key2="v2"?>`.
+	/// The values are quoted with the configured [`Quote`], `&`, `<` and the delimiting quote are escaped,
+	/// the other quote is kept.
+	/// Like [`XmlWriter::pi`] it may be written in the prolog as well as between elements.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if `target` or a key is not a valid `NCName` or `target` is the reserved `xml` target,
+	///   nothing is written in that case
+	/// - if a value contains `?>`, nothing is written in that case
+	pub fn pi_attrs<'v, I>(&mut self, target: &str, attrs: I) -> Result<(), Error>
+	where
+		I: IntoIterator<Item = (&'v str, &'v str)>,
+		I::IntoIter: Clone,
+	{
+		let attrs = attrs.into_iter();
+		if !is_ncname(target) || target.eq_ignore_ascii_case("xml") || attrs.clone().any(|(key, _)| !is_ncname(key)) {
+			return Err(Error::InvalidName);
+		}
+		if attrs
+			.clone()
+			.any(|(_, value)| value.contains("?>"))
+		{
+			return Err(Error::InvalidProcessingInstruction);
+		}
+		let quote = self.config.quote;
+		self.record(|| Event::Pi {
+			target: target.into(),
+			data: attrs
+				.clone()
+				.map(|(key, value)| format!("{key}{}{value}{}", quote.open(), quote.close()))
+				.collect::<Vec<_>>()
+				.join(" "),
+		})?;
+		self.open_child()?;
+		self.indent()?;
+		self.write("<?")?;
+		self.write(target)?;
+		for (key, value) in attrs {
+			self.write(SPACE)?;
+			self.write(key)?;
+			self.write(self.config.quote.open())?;
+			self.escape(value, Escape::Minimal)?;
+			self.write(self.config.quote.close())?;
+		}
+		self.write("?>")
+	}
+
+	/// Write a marker comment like `<!-- region: generated -->` for other tools to parse.
+	/// In contrast to [`XmlWriter::comment`] the text is validated to be a single line without `--`
+	/// and written as is.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - if the text contains a line break or `--`, nothing is written in that case
+	pub fn marker_comment(&mut self, text: &str) -> Result<(), Error> {
+		if text.contains(['\n', '\r']) {
+			return Err(Error::InvalidComment);
+		}
+		self.comment_raw(text)
+	}
+
+	/// Write a provenance comment like `<!-- generated by tool v1.2.3 at 2025-01-01 -->` in the prolog.
+	/// The `timestamp` is supplied by the caller, as there is no clock in `no_std` environments.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - [`Error::ContentAlreadyWritten`] if an element has been written before, nothing is written in that case
+	/// - [`Error::InvalidComment`] if an input contains `--`, nothing is written in that case
+	pub fn provenance_comment(&mut self, tool: &str, version: &str, timestamp: Option<&str>) -> Result<(), Error> {
+		if self.has_root {
+			return Err(Error::ContentAlreadyWritten);
+		}
+		if [tool, version, timestamp.unwrap_or_default()]
+			.iter()
+			.any(|input| input.contains("--"))
+		{
+			return Err(Error::InvalidComment);
+		}
+		self.record(|| {
+			Event::Comment(timestamp.map_or_else(
+				|| format!("generated by {tool} v{version}"),
+				|timestamp| format!("generated by {tool} v{version} at {timestamp}"),
+			))
+		})?;
+		self.indent()?;
+		self.write("<!-- generated by ")?;
+		self.write(tool)?;
+		self.write(" v")?;
+		self.write(version)?;
+		if let Some(timestamp) = timestamp {
+			self.write(" at ")?;
+			self.write(timestamp)?;
+		}
 		self.write(" -->")
 	}
 
@@ -391,20 +2663,433 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 		Ok(())
 	}
 
+	/// Close open elems until the innermost elem named `name` is the current one.
+	/// If `inclusive` is `true`, that elem is closed as well.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - when no elem named `name` is open, nothing is closed in that case
+	pub fn close_to(&mut self, name: &str, inclusive: bool) -> Result<(), Error> {
+		let Some(index) = self.stack.iter().rposition(|(n, _)| *n == name) else {
+			return Err(Error::CloseElement);
+		};
+		let depth = if inclusive { index } else { index + 1 };
+		while self.stack.len() > depth {
+			self.end_elem()?;
+		}
+		Ok(())
+	}
+
+	/// Get the path of the currently open elems, e.g. `OTDS/st:node/stuff`,
+	/// useful for diagnostics.
+	#[must_use]
+	pub fn element_path(&self) -> String {
+		let mut path = Vec::new();
+		// writing into a `Vec` can not fail
+		let _ = self.write_path(&mut path);
+		String::from_utf8(path).unwrap_or_default()
+	}
+
+	/// Write the path of the currently open elems into `sink` without any allocation,
+	/// see [`XmlWriter::element_path`].
+	/// # Errors
+	/// - if writing to `sink` fails
+	pub fn write_path<S: Write>(&self, sink: &mut S) -> Result<(), Error> {
+		for (index, ((name, _), ns)) in self.stack.iter().zip(&self.ns_stack).enumerate() {
+			if index > 0 {
+				sink.write_all(b"/")?;
+			}
+			if let Some(ns) = ns {
+				sink.write_all(ns.as_bytes())?;
+				sink.write_all(b":")?;
+			}
+			sink.write_all(name.as_bytes())?;
+		}
+		Ok(())
+	}
+
+	/// Reformat the output written so far for human inspection, e.g. of attribute-heavy documents:
+	/// each tag, comment and text on its own line indented by its depth,
+	/// each attribute on its own line below its start tag.
+	/// This is a diagnostic aid parsing the output lightly, not a serialization mode.
+	/// It requires a buffer able to provide its bytes like `Vec<u8>` or `BytesMut`,
+	/// see [`Write::as_bytes`], otherwise only pending output is dumped.
+	/// ```
+	/// use woxml::XmlWriter;
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.begin_elem("root")?;
+	/// xml.attr("a", "1")?;
+	/// xml.attr("b", "2")?;
+	/// xml.empty_elem("child")?;
+	/// xml.end_elem()?;
+	/// assert_eq!(xml.debug_dump(), "<root\n    a=\"1\"\n    b=\"2\">\n  <child/>\n</root>");
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	#[must_use]
+	pub fn debug_dump(&self) -> String {
+		let written = self.buffer.as_bytes().unwrap_or_default();
+		if self.pending.is_empty() {
+			scanner::dump(written)
+		} else {
+			scanner::dump(&[written, &self.pending].concat())
+		}
+	}
+
+	/// Get the currently open elems, outermost first, each with a flag whether it has children.
+	/// This allows tooling to mirror the context of the writer, see also [`XmlWriter::namespace_stack`].
+	#[must_use]
+	pub const fn element_stack(&self) -> &[(&'a str, bool)] {
+		self.stack.as_slice()
+	}
+
+	/// Get the namespace prefixes of the currently open elems, outermost first,
+	/// in parallel to [`XmlWriter::element_stack`].
+	#[must_use]
+	pub const fn namespace_stack(&self) -> &[Option<&'a str>] {
+		self.ns_stack.as_slice()
+	}
+
+	/// Take a snapshot of the current state, which can be restored with [`XmlWriter::rollback`].
+	/// This allows speculative writing, e.g. of content that may turn out to be empty.
+	/// Attributes collected for sorting or ordering, see [`XmlWriter::set_sort_attributes`]
+	/// and [`XmlWriter::set_ns_decl_order`], are only kept while their element is open.
+	/// A snapshot taken within a start tag with collected attributes can't be restored
+	/// once the element is closed, the rollback fails with [`Error::InvalidCheckpoint`] then.
+	#[must_use]
+	pub fn checkpoint(&self) -> Checkpoint<'a> {
+		Checkpoint {
+			stack: self.stack.clone(),
+			ns_stack: self.ns_stack.clone(),
+			ns_scopes: self.ns_scopes.clone(),
+			ns_declared: self.ns_declared.clone(),
+			namespace: self.namespace,
+			default_namespace: self.default_namespace,
+			default_declared: self.default_declared,
+			default_ns_scopes: self.default_ns_scopes.clone(),
+			opened: self.opened,
+			newline: self.newline,
+			skip_indent: self.skip_indent,
+			preserve_depth: self.preserve_depth,
+			top_level_child: self.top_level_child,
+			has_root: self.has_root,
+			multiple_roots: self.multiple_roots,
+			bytes_written: self.bytes_written,
+			staged: self.staged.len(),
+			staged_spans: self.staged_spans.len(),
+			staged_flushed: self.staged_flushed,
+			ordered: self.ordered,
+			events: self.events.as_ref().map_or(0, Vec::len),
+			elem_starts: self.elem_starts.clone(),
+		}
+	}
+
+	/// Restore the state of a [`Checkpoint`], discarding everything written since.
+	/// Rollback is only supported for buffers which can be truncated, see [`Write::truncate`].
+	/// # Errors
+	/// - if the buffer does not support truncation
+	/// - if the checkpoint does not belong to the current output,
+	///   or attributes were staged at the checkpoint and their elem has been closed since, see [`XmlWriter::set_sort_attributes`]
+	pub fn rollback(&mut self, checkpoint: Checkpoint<'a>) -> Result<(), Error> {
+		// the staged attributes of the open elem are only kept as long as it is open
+		let open = checkpoint.elem_starts.last().is_none_or(|start| {
+			self.elem_starts
+				.get(checkpoint.elem_starts.len() - 1)
+				.is_some_and(|current| current.bytes_written == start.bytes_written)
+		});
+		if checkpoint.staged_flushed < checkpoint.staged_spans
+			&& (!open || self.staged_spans.len() < checkpoint.staged_spans)
+		{
+			return Err(Error::InvalidCheckpoint);
+		}
+		self.truncate_output(checkpoint.bytes_written)?;
+		self.stack = checkpoint.stack;
+		self.ns_stack = checkpoint.ns_stack;
+		self.ns_scopes = checkpoint.ns_scopes;
+		self.ns_declared = checkpoint.ns_declared;
+		self.namespace = checkpoint.namespace;
+		self.default_namespace = checkpoint.default_namespace;
+		self.default_declared = checkpoint.default_declared;
+		self.default_ns_scopes = checkpoint.default_ns_scopes;
+		self.opened = checkpoint.opened;
+		self.newline = checkpoint.newline;
+		self.skip_indent = checkpoint.skip_indent;
+		self.preserve_depth = checkpoint.preserve_depth;
+		self.top_level_child = checkpoint.top_level_child;
+		self.has_root = checkpoint.has_root;
+		self.multiple_roots = checkpoint.multiple_roots;
+		self.bytes_written = checkpoint.bytes_written;
+		// staged attributes may have been written since, they are staged again in their original order
+		if let Some(spans) = self
+			.staged_spans
+			.get_mut(checkpoint.staged_flushed..)
+		{
+			spans.sort_by_key(|(_, _, start, _)| *start);
+		}
+		self.discard_staged(checkpoint.staged_spans);
+		self.staged.truncate(checkpoint.staged);
+		self.staged_flushed = checkpoint
+			.staged_flushed
+			.min(self.staged_spans.len());
+		self.ordered = checkpoint.ordered;
+		if let Some(events) = self.events.as_mut() {
+			events.truncate(checkpoint.events);
+		}
+		self.elem_starts = checkpoint.elem_starts;
+		self.last_elem = None;
+		self.text_end = None;
+		Ok(())
+	}
+
+	/// Discard the output written since `bytes_written` bytes had been written.
+	/// # Errors
+	/// - if the buffer does not support truncation
+	/// - if the output is shorter than `bytes_written`
+	fn truncate_output(&mut self, bytes_written: usize) -> Result<(), Error> {
+		let excess = self
+			.bytes_written
+			.checked_sub(bytes_written)
+			.ok_or(Error::InvalidCheckpoint)?;
+		if let Some(len) = self.pending.len().checked_sub(excess) {
+			self.pending.truncate(len);
+		} else {
+			let len = self
+				.buffer
+				.len()
+				.ok_or(Error::TruncateUnsupported)?;
+			let len = len
+				.checked_sub(excess - self.pending.len())
+				.ok_or(Error::InvalidCheckpoint)?;
+			self.buffer.truncate(len)?;
+			self.pending.clear();
+		}
+		self.last_flush_count = self.last_flush_count.min(bytes_written);
+		Ok(())
+	}
+
+	/// Remove the last element again, if it has been closed by the most recent write,
+	/// e.g. because it turned out to be empty.
+	/// The start tag of its parent stays closed, so the parent will not be self-closing anymore.
+	/// Only elements begun with [`XmlWriter::begin_elem`] or written with [`XmlWriter::empty_elem`] can be removed,
+	/// and like [`XmlWriter::rollback`] this is only supported for buffers which can be truncated.
+	/// ```
+	/// use woxml::XmlWriter;
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.begin_elem("list")?;
+	/// xml.empty_elem("kept")?;
+	/// xml.begin_elem("item")?;
+	/// xml.end_elem()?;
+	/// xml.pop_last_element()?;
+	/// xml.end_elem()?;
+	/// assert_eq!(String::try_from(xml)?, "<list><kept/></list>");
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	/// # Errors
+	/// - [`Error::NoLastElement`] if the most recent write did not close an element
+	/// - if the buffer does not support truncation
+	pub fn pop_last_element(&mut self) -> Result<(), Error> {
+		let start = match self.last_elem {
+			Some((start, end)) if end == self.bytes_written => start,
+			_ => return Err(Error::NoLastElement),
+		};
+		self.truncate_output(start.bytes_written)?;
+		self.last_elem = None;
+		self.newline = start.newline;
+		self.skip_indent = start.skip_indent;
+		self.top_level_child = start.top_level_child;
+		self.has_root = start.has_root;
+		self.multiple_roots = start.multiple_roots;
+		self.bytes_written = start.bytes_written;
+		if let Some(events) = self.events.as_mut() {
+			events.truncate(start.events);
+		}
+		Ok(())
+	}
+
+	/// The state before the start tag of an element
+	const fn elem_start(&self) -> ElemStart {
+		ElemStart {
+			newline: self.newline,
+			skip_indent: self.skip_indent,
+			top_level_child: self.top_level_child,
+			has_root: self.has_root,
+			multiple_roots: self.multiple_roots,
+			bytes_written: self.bytes_written,
+			events: match &self.events {
+				Some(events) => events.len(),
+				None => 0,
+			},
+		}
+	}
+
+	/// The number of bytes that can still be written to a fixed-size Writer,
+	/// `None` if the Writer is unbounded, see [`Write::remaining`].
+	/// Pending output, see [`XmlWriter::set_buffer_size`], is already taken into account.
+	#[must_use]
+	pub fn remaining_capacity(&self) -> Option<usize> {
+		self.buffer
+			.remaining()
+			.map(|remaining| remaining.saturating_sub(self.pending.len()))
+	}
+
+	/// Returns `true` if no element has been written yet.
+	#[must_use]
+	pub const fn is_document_empty(&self) -> bool {
+		!self.has_root
+	}
+
+	/// Finish the output: close all open elems and flush the underlying Writer.
+	/// # Errors
+	/// - if writing to buffer fails
+	/// - in document mode, see [`XmlWriter::set_document_mode`], if not exactly one root element has been written
+	/// - if the output is malformed and validation is enabled, see [`XmlWriter::set_validate_on_finish`]
+	pub fn finish(&mut self) -> Result<(), Error> {
+		self.close()?;
+		self.flush()?;
+		self.check_document()?;
+		if self.config.validate_on_finish
+			&& let Some(bytes) = self.buffer.as_bytes()
+		{
+			scanner::check(bytes, self.config.document).map_err(|reason| Error::MalformedOutput { reason })?;
+		}
+		Ok(())
+	}
+
+	/// Check for exactly one root element in document mode
+	const fn check_document(&self) -> Result<(), Error> {
+		if self.config.document {
+			if !self.has_root {
+				return Err(Error::EmptyDocument);
+			}
+			if self.multiple_roots {
+				return Err(Error::MultipleRoots);
+			}
+		}
+		Ok(())
+	}
+
 	/// Flush the underlying Writer
 	/// # Errors
 	/// - if writing to buffer fails
 	pub fn flush(&mut self) -> Result<(), Error> {
+		self.write_pending()?;
 		self.buffer.flush()?;
+		self.last_flush_count = self.bytes_written;
 
 		Ok(())
 	}
 
-	/// Consume the `XmlWriter` and return the inner Writer
+	/// Flush the underlying Writer like [`XmlWriter::flush`]
+	/// and return the number of bytes written since the previous flush,
+	/// e.g. to frame incrementally sent output by size.
+	/// Output discarded by a rollback is not counted.
+	/// # Errors
+	/// - if writing to buffer fails
+	pub fn flush_and_count(&mut self) -> Result<usize, Error> {
+		let previous = self.last_flush_count;
+		self.flush()?;
+		Ok(self.bytes_written.saturating_sub(previous))
+	}
+
+	/// Replace the inner Writer with `buffer` and return the old one, e.g. to forward completed output.
+	/// The state of open elements and namespaces is kept, so writing continues into `buffer`.
+	///
+	/// The output is split where it currently is, e.g. within the start tag of an open element,
+	/// so neither part is well-formed XML on its own. [`Checkpoint`]s taken before are invalid afterwards.
+	/// Pending output, see [`XmlWriter::set_buffer_size`], is handed to the old Writer on a best effort basis,
+	/// use [`XmlWriter::flush`] before to handle errors.
+	pub fn replace_buffer(&mut self, buffer: W) -> W {
+		let _ = self.write_pending();
+		*core::mem::replace(&mut self.buffer, Box::new(buffer))
+	}
+
+	/// Take the inner Writer, leaving an empty one in its place,
+	/// see [`XmlWriter::replace_buffer`] for the caveats.
+	/// ```
+	/// use woxml::XmlWriter;
+	///
+	/// let mut xml = XmlWriter::compact_mode(Vec::new());
+	/// xml.begin_elem("log")?;
+	/// xml.empty_elem("entry")?;
+	/// assert_eq!(xml.take_buffer(), b"<log><entry/>");
+	/// xml.end_elem()?;
+	/// assert_eq!(xml.take_buffer(), b"</log>");
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	pub fn take_buffer(&mut self) -> W
+	where
+		W: Default,
+	{
+		self.replace_buffer(W::default())
+	}
+
+	/// Consume the `XmlWriter` and return the inner Writer.
+	/// Pending output, see [`XmlWriter::set_buffer_size`], is handed to the Writer on a best effort basis,
+	/// use [`XmlWriter::flush`] before to handle errors.
 	#[must_use]
-	pub fn into_inner(self) -> W {
+	pub fn into_inner(mut self) -> W {
+		let _ = self.write_pending();
 		*self.buffer
 	}
+
+	/// Consume the `XmlWriter` and return the inner Writer, if all elements are closed.
+	/// In contrast to [`XmlWriter::into_inner`] pending output is handed to the Writer with error handling.
+	/// # Errors
+	/// - [`Error::UnclosedElements`] if elements are still open, see [`XmlWriter::close`]
+	/// - in document mode, see [`XmlWriter::set_document_mode`], if not exactly one root element has been written
+	/// - if writing pending output to buffer fails
+	pub fn try_into_inner(mut self) -> Result<W, Error> {
+		if !self.stack.is_empty() {
+			return Err(Error::UnclosedElements { count: self.stack.len() });
+		}
+		self.check_document()?;
+		self.write_pending()?;
+		Ok(*self.buffer)
+	}
+}
+
+impl<'a> XmlWriter<'a, Discard> {
+	/// Create a writer without output, which sends the [`Event`]s of all operations to `sink`,
+	/// e.g. to drive another serialization backend with the state handling and checks of the writer.
+	/// ```
+	/// use woxml::{Error, Event, EventSink, XmlWriter};
+	///
+	/// #[derive(Default)]
+	/// struct Names(Vec<String>);
+	///
+	/// impl EventSink<'_> for Names {
+	///     fn event(&mut self, event: Event<'_>) -> Result<(), Error> {
+	///         if let Event::StartElem { name, .. } = event {
+	///             self.0.push(name.into_owned());
+	///         }
+	///         Ok(())
+	///     }
+	/// }
+	///
+	/// let mut names = Names::default();
+	/// let mut xml = XmlWriter::event_mode(&mut names);
+	/// xml.begin_elem("root")?;
+	/// xml.empty_elem("child")?;
+	/// xml.end_elem()?;
+	/// drop(xml);
+	/// assert_eq!(names.0, ["root", "child"]);
+	/// # Ok::<(), woxml::Error>(())
+	/// ```
+	pub fn event_mode(sink: impl EventSink<'a> + Send + 'a) -> Self {
+		let mut xml = Self::compact_mode(Discard);
+		xml.set_event_sink(sink);
+		xml
+	}
+}
+
+impl XmlWriter<'_, bytes::BytesMut> {
+	/// Consume the `XmlWriter` and return the output as immutable, cheaply cloneable [`bytes::Bytes`]
+	/// without copying it, e.g. to send it with async networking code.
+	/// Pending output is handed over like in [`XmlWriter::into_inner`], which never fails for [`bytes::BytesMut`].
+	#[must_use]
+	pub fn into_bytes(self) -> bytes::Bytes {
+		self.into_inner().freeze()
+	}
 }
 
 //==== Implementations ====
@@ -413,7 +3098,8 @@ impl<'a, W: Write> XmlWriter<'a, W> {
 impl<'a> TryFrom<XmlWriter<'a, Vec<u8>>> for String {
 	type Error = Error;
 
-	fn try_from(writer: XmlWriter<'a, Vec<u8>>) -> core::result::Result<Self, Self::Error> {
+	fn try_from(mut writer: XmlWriter<'a, Vec<u8>>) -> core::result::Result<Self, Self::Error> {
+		writer.write_pending()?;
 		Self::from_utf8(writer.into_inner()).map_or(Err(Error::ParsingUtf8), Ok)
 	}
 }
@@ -422,7 +3108,29 @@ impl<'a> TryFrom<XmlWriter<'a, Vec<u8>>> for String {
 impl<'a> TryFrom<XmlWriter<'a, bytes::BytesMut>> for String {
 	type Error = Error;
 
-	fn try_from(writer: XmlWriter<'a, bytes::BytesMut>) -> core::result::Result<Self, Self::Error> {
+	fn try_from(mut writer: XmlWriter<'a, bytes::BytesMut>) -> core::result::Result<Self, Self::Error> {
+		writer.write_pending()?;
+		Self::from_utf8(writer.into_inner().to_vec()).map_or(Err(Error::ParsingUtf8), Ok)
+	}
+}
+
+/// Fallible conversion to [`String`] for a bounded [`bytes::BytesMut`].
+impl<'a> TryFrom<XmlWriter<'a, bytes::buf::Limit<bytes::BytesMut>>> for String {
+	type Error = Error;
+
+	fn try_from(mut writer: XmlWriter<'a, bytes::buf::Limit<bytes::BytesMut>>) -> core::result::Result<Self, Self::Error> {
+		writer.write_pending()?;
+		Self::from_utf8(writer.into_inner().into_inner().to_vec()).map_or(Err(Error::ParsingUtf8), Ok)
+	}
+}
+
+/// Fallible conversion to [`String`] for [`arrayvec::ArrayVec<u8, N>`].
+#[cfg(feature = "arrayvec")]
+impl<'a, const N: usize> TryFrom<XmlWriter<'a, arrayvec::ArrayVec<u8, N>>> for String {
+	type Error = Error;
+
+	fn try_from(mut writer: XmlWriter<'a, arrayvec::ArrayVec<u8, N>>) -> core::result::Result<Self, Self::Error> {
+		writer.write_pending()?;
 		Self::from_utf8(writer.into_inner().to_vec()).map_or(Err(Error::ParsingUtf8), Ok)
 	}
 }
@@ -19,6 +19,38 @@ pub trait Write {
 	/// It is not considered an error if the entire buffer could not be written to this writer.
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
 
+	/// Returns the number of bytes currently held by this writer, if it is able to tell.
+	/// The default implementation returns `None`.
+	fn len(&self) -> Option<usize> {
+		None
+	}
+
+	/// Returns `true` if this writer holds no bytes, if it is able to tell.
+	fn is_empty(&self) -> Option<bool> {
+		self.len().map(|len| len == 0)
+	}
+
+	/// Returns the bytes currently held by this writer, if it is able to provide them.
+	/// The default implementation returns `None`.
+	fn as_bytes(&self) -> Option<&[u8]> {
+		None
+	}
+
+	/// Returns the number of bytes that can still be written to a fixed-size writer.
+	/// The default implementation returns `None` for unbounded writers.
+	fn remaining(&self) -> Option<usize> {
+		None
+	}
+
+	/// Shortens the content of this writer to `len` bytes.
+	/// The default implementation does not support truncation.
+	/// # Errors
+	/// - if truncation is not supported by this writer
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		let _ = len;
+		Err(Error::TruncateUnsupported)
+	}
+
 	/// Attempts to write an entire buffer into this writer.
 	///
 	/// This method shall continuously call write until there is no more data to be written.
@@ -51,9 +83,26 @@ impl Write for alloc::vec::Vec<u8> {
 		self.extend_from_slice(buf);
 		Ok(buf.len())
 	}
+
+	#[inline]
+	fn len(&self) -> Option<usize> {
+		Some(<[u8]>::len(self))
+	}
+
+	#[inline]
+	fn as_bytes(&self) -> Option<&[u8]> {
+		Some(self)
+	}
+
+	#[inline]
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		Self::truncate(self, len);
+		Ok(())
+	}
 }
 
 /// [`Write`] implementation for [`bytes::BytesMut`].
+/// The buffer grows as needed, so writing is infallible, for a bounded buffer see [`bytes::buf::Limit`].
 impl Write for bytes::BytesMut {
 	#[inline]
 	fn flush(&mut self) -> Result<(), Error> {
@@ -65,4 +114,177 @@ impl Write for bytes::BytesMut {
 		self.extend_from_slice(buf);
 		Ok(buf.len())
 	}
+
+	#[inline]
+	fn len(&self) -> Option<usize> {
+		Some(<[u8]>::len(self))
+	}
+
+	#[inline]
+	fn as_bytes(&self) -> Option<&[u8]> {
+		Some(self)
+	}
+
+	#[inline]
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		Self::truncate(self, len);
+		Ok(())
+	}
+}
+
+/// [`Write`] implementation for a [`bytes::BytesMut`] bounded with [`bytes::BufMut::limit`].
+/// Writes as many bytes as fit into the remaining limit.
+impl Write for bytes::buf::Limit<bytes::BytesMut> {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let n = bytes::BufMut::remaining_mut(self).min(buf.len());
+		if n == 0 {
+			return Err(Error::BufferFull);
+		}
+		bytes::BufMut::put_slice(self, &buf[..n]);
+		Ok(n)
+	}
+
+	#[inline]
+	fn len(&self) -> Option<usize> {
+		Some(self.get_ref().len())
+	}
+
+	#[inline]
+	fn as_bytes(&self) -> Option<&[u8]> {
+		Some(self.get_ref())
+	}
+
+	#[inline]
+	fn remaining(&self) -> Option<usize> {
+		Some(bytes::BufMut::remaining_mut(self))
+	}
+
+	/// Truncation gives the removed bytes back to the limit.
+	#[inline]
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		let removed = self.get_ref().len().saturating_sub(len);
+		self.get_mut().truncate(len);
+		self.set_limit(self.limit() + removed);
+		Ok(())
+	}
+}
+
+/// [`Write`] implementation for [`arrayvec::ArrayVec<u8, N>`].
+/// Writes as many bytes as fit into the remaining capacity.
+#[cfg(feature = "arrayvec")]
+impl<const N: usize> Write for arrayvec::ArrayVec<u8, N> {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let n = self.remaining_capacity().min(buf.len());
+		if n == 0 {
+			return Err(Error::BufferFull);
+		}
+		self.try_extend_from_slice(&buf[..n])
+			.map_err(|_| Error::BufferFull)?;
+		Ok(n)
+	}
+
+	#[inline]
+	fn len(&self) -> Option<usize> {
+		Some(<[u8]>::len(self))
+	}
+
+	#[inline]
+	fn as_bytes(&self) -> Option<&[u8]> {
+		Some(self)
+	}
+
+	#[inline]
+	fn remaining(&self) -> Option<usize> {
+		Some(self.remaining_capacity())
+	}
+
+	#[inline]
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		Self::truncate(self, len);
+		Ok(())
+	}
+}
+
+/// [`Write`] implementation for a mutable byte slice.
+/// Like `std::io::Write` for `&mut [u8]` the slice is advanced past the written bytes,
+/// so it always refers to the not yet written remainder.
+impl Write for &mut [u8] {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		let n = <[u8]>::len(self).min(buf.len());
+		if n == 0 {
+			return Err(Error::BufferFull);
+		}
+		let (head, tail) = core::mem::take(self).split_at_mut(n);
+		head.copy_from_slice(&buf[..n]);
+		*self = tail;
+		Ok(n)
+	}
+
+	#[inline]
+	fn remaining(&self) -> Option<usize> {
+		Some(<[u8]>::len(self))
+	}
+}
+
+/// [`Write`] implementation for a shared [`std::sync::Arc<std::sync::Mutex<W>>`](std::sync::Mutex),
+/// so that writers in several threads can append to the same sink.
+/// The lock is held for each call only, so output of different writers
+/// is interleaved at the granularity of their single writes.
+#[cfg(feature = "std")]
+impl<W: Write> Write for std::sync::Arc<std::sync::Mutex<W>> {
+	#[inline]
+	fn flush(&mut self) -> Result<(), Error> {
+		self.lock()
+			.map_err(|_| Error::LockPoisoned)?
+			.flush()
+	}
+
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		self.lock()
+			.map_err(|_| Error::LockPoisoned)?
+			.write(buf)
+	}
+
+	#[inline]
+	fn remaining(&self) -> Option<usize> {
+		self.lock()
+			.ok()
+			.and_then(|writer| writer.remaining())
+	}
+
+	/// Writes the entire buffer while holding the lock once.
+	#[inline]
+	fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+		self.lock()
+			.map_err(|_| Error::LockPoisoned)?
+			.write_all(buf)
+	}
 }
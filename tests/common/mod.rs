@@ -0,0 +1,47 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Helpers shared by the integration tests.
+#![allow(clippy::panic)]
+
+use quick_xml::{Reader, XmlVersion, events::Event};
+
+/// Parse `xml` with a real XML parser and panic if it is not a well-formed fragment.
+/// Multiple top level elements are accepted, everything else has to conform to the XML spec.
+pub fn assert_well_formed(xml: &str) {
+	let mut reader = Reader::from_str(xml);
+	let mut depth = 0_usize;
+	loop {
+		match reader.read_event() {
+			Ok(Event::Start(elem)) => {
+				for attr in elem.attributes() {
+					let attr = attr.unwrap_or_else(|err| panic!("malformed attribute ({err}) in: {xml}"));
+					attr.normalized_value(XmlVersion::Implicit1_0)
+						.unwrap_or_else(|err| panic!("malformed attribute value ({err}) in: {xml}"));
+				}
+				depth += 1;
+			}
+			Ok(Event::Empty(elem)) => {
+				for attr in elem.attributes() {
+					let attr = attr.unwrap_or_else(|err| panic!("malformed attribute ({err}) in: {xml}"));
+					attr.normalized_value(XmlVersion::Implicit1_0)
+						.unwrap_or_else(|err| panic!("malformed attribute value ({err}) in: {xml}"));
+				}
+			}
+			Ok(Event::End(_)) => depth -= 1,
+			Ok(Event::GeneralRef(reference)) => {
+				let known = matches!(&*reference.xml10_content(), "amp" | "lt" | "gt" | "apos" | "quot");
+				assert!(
+					known
+						|| reference
+							.resolve_char_ref()
+							.is_ok_and(|c| c.is_some()),
+					"undefined entity reference in: {xml}"
+				);
+			}
+			Ok(Event::Eof) => break,
+			Ok(_) => {}
+			Err(err) => panic!("not well-formed ({err}): {xml}"),
+		}
+	}
+	assert_eq!(depth, 0, "unclosed elements in: {xml}");
+}
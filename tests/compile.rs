@@ -0,0 +1,11 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Compile time checks of the `elem_name!` macro.
+#![cfg(feature = "std")]
+
+#[test]
+fn elem_name_compile_time_validation() {
+	let cases = trybuild::TestCases::new();
+	cases.pass("tests/ui/elem_name_pass.rs");
+	cases.compile_fail("tests/ui/elem_name_fail.rs");
+}
@@ -0,0 +1,90 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Property tests of the escaping: arbitrary text and attribute values are written,
+//! parsed again with a real XML parser and have to match the original.
+#![cfg(feature = "std")]
+#![allow(clippy::unwrap_used)]
+
+use proptest::prelude::*;
+use quick_xml::{Reader, XmlVersion, events::Event};
+use woxml::{Config, Error, Quote, XmlWriter};
+
+/// Any XML characters, except `\r`, which a parser normalizes in text content
+const TEXT: &str = "[\t\n\x20-\u{D7FF}\u{E000}-\u{FFFD}\u{10000}-\u{10FFFF}]*";
+/// Any XML characters, except whitespace, which a parser normalizes in attribute values
+const VALUE: &str = "[\x20-\u{D7FF}\u{E000}-\u{FFFD}\u{10000}-\u{10FFFF}]*";
+/// Any XML characters
+const ANY: &str = "[\t\n\r\x20-\u{D7FF}\u{E000}-\u{FFFD}\u{10000}-\u{10FFFF}]*";
+
+/// Write an element with the attribute value `value` and the text content `text`.
+fn write(config: Config, value: &str, text: &str) -> Result<String, Error> {
+	let mut xml = XmlWriter::with_config(Vec::new(), config);
+	xml.begin_elem("root")?;
+	xml.attr_esc("value", value)?;
+	xml.text(text)?;
+	xml.end_elem()?;
+	String::try_from(xml)
+}
+
+/// Parse the output of [`write`], returning the attribute value and the text content.
+fn parse(xml: &str) -> Result<(String, String), String> {
+	let mut reader = Reader::from_str(xml);
+	let mut value = None;
+	let mut text = String::new();
+	loop {
+		match reader
+			.read_event()
+			.map_err(|err| err.to_string())?
+		{
+			Event::Start(elem) | Event::Empty(elem) => {
+				let attr = elem
+					.attributes()
+					.next()
+					.ok_or("missing attribute")?
+					.map_err(|err| err.to_string())?;
+				value = Some(
+					attr.normalized_value(XmlVersion::Implicit1_0)
+						.map_err(|err| err.to_string())?
+						.into_owned(),
+				);
+			}
+			Event::Text(content) => text.push_str(&content.xml10_content()),
+			Event::GeneralRef(reference) => match &*reference.xml10_content() {
+				"amp" => text.push('&'),
+				"lt" => text.push('<'),
+				"gt" => text.push('>'),
+				"apos" => text.push('\''),
+				"quot" => text.push('"'),
+				_ => text.push(
+					reference
+						.resolve_char_ref()
+						.map_err(|err| err.to_string())?
+						.ok_or("unknown entity")?,
+				),
+			},
+			Event::Eof => break,
+			_ => {}
+		}
+	}
+	Ok((value.ok_or("missing element")?, text))
+}
+
+proptest! {
+	#[test]
+	fn roundtrip_double_quote(value in VALUE, text in TEXT) {
+		let xml = write(Config::compact(), &value, &text).unwrap();
+		prop_assert_eq!(parse(&xml), Ok((value, text)), "{}", xml);
+	}
+
+	#[test]
+	fn roundtrip_single_quote(value in VALUE, text in TEXT) {
+		let xml = write(Config::pretty().with_quote(Quote::Single), &value, &text).unwrap();
+		prop_assert_eq!(parse(&xml), Ok((value, text)), "{}", xml);
+	}
+
+	#[test]
+	fn roundtrip_canonical(value in ANY, text in ANY) {
+		let xml = write(Config::canonical(), &value, &text).unwrap();
+		prop_assert_eq!(parse(&xml), Ok((value, text)), "{}", xml);
+	}
+}
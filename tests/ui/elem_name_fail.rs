@@ -0,0 +1,10 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Invalid names fail to compile with `elem_name!`.
+
+use woxml::elem_name;
+
+fn main() {
+	let _ = elem_name!("1bad");
+	let _ = elem_name!("has space");
+}
@@ -0,0 +1,13 @@
+// Copyright © 2026 Stephan Kunz
+
+//! Valid names are accepted by `elem_name!`.
+
+use woxml::{XmlWriter, elem_name};
+
+fn main() -> Result<(), woxml::Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem(elem_name!("foo-bar"))?;
+	xml.empty_elem(elem_name!("ns:_item.1"))?;
+	xml.end_elem()?;
+	Ok(())
+}
@@ -10,7 +10,16 @@ extern crate std;
 use alloc::{format, str, string::String, vec, vec::Vec};
 #[cfg(feature = "std")]
 use std::println;
-use woxml::{Error, XmlWriter};
+use woxml::{ApostropheStyle, CdataPolicy, Config, Error, Event, EventSink, IndentUnit, NsOrder, Quote, XmlWriter};
+
+#[cfg(feature = "std")]
+mod common;
+#[cfg(feature = "std")]
+use common::assert_well_formed;
+
+/// Well-formedness is only checked with a parser when `std` is available.
+#[cfg(not(feature = "std"))]
+const fn assert_well_formed(_xml: &str) {}
 
 // ---- dtd -----------------------------------------------------------------------
 
@@ -22,6 +31,7 @@ fn dtd_compact() -> Result<(), Error> {
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<root/>");
 	Ok(())
 }
@@ -34,10 +44,62 @@ fn dtd_pretty() -> Result<(), Error> {
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<root/>");
 	Ok(())
 }
 
+#[test]
+fn document_prolog() -> Result<(), Error> {
+	let ns_map = vec![(None, "urn:default"), (Some("x"), "urn:x")];
+	let mut manual = XmlWriter::pretty_mode(Vec::new());
+	manual.dtd("UTF-8")?;
+	manual.begin_elem("root")?;
+	manual.ns_decl(&ns_map)?;
+	manual.empty_elem("child")?;
+	manual.end_elem()?;
+
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.document("UTF-8", "root", &ns_map)?;
+	xml.empty_elem("child")?;
+	assert_eq!(xml.document("UTF-8", "root", &ns_map), Err(Error::ContentAlreadyWritten));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(res, String::try_from(manual).unwrap());
+	Ok(())
+}
+
+#[test]
+fn doctype_raw_xhtml() -> Result<(), Error> {
+	let doctype = concat!(
+		r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "#,
+		r#""http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd">"#
+	);
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.dtd("UTF-8")?;
+	xml.doctype_raw(doctype)?;
+	xml.begin_elem("html")?;
+	xml.empty_elem("body")?;
+	xml.end_elem()?;
+	assert_eq!(xml.doctype_raw("<!DOCTYPE html>"), Err(Error::ContentAlreadyWritten));
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(
+		res,
+		format!("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n{doctype}\n<html>\n  <body/>\n</html>")
+	);
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.doctype_raw("<!DOCTYPE html>")?;
+	xml.empty_elem("html")?;
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<!DOCTYPE html><html/>");
+	Ok(())
+}
+
 // ---- elem / elem_text ----------------------------------------------------------
 
 #[test]
@@ -51,6 +113,7 @@ fn elem_self_closing() -> Result<(), Error> {
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<root/><br/><hr/>");
 	Ok(())
 }
@@ -62,6 +125,7 @@ fn elem_standalone() -> Result<(), Error> {
 	xml.elem("br")?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<br/>");
 	Ok(())
 }
@@ -76,6 +140,7 @@ fn elem_with_namespace() -> Result<(), Error> {
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<ns:root/><ns:child/>");
 	Ok(())
 }
@@ -89,6 +154,7 @@ fn elem_text_inline() -> Result<(), Error> {
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<root/><title>Hello World</title>");
 	Ok(())
 }
@@ -100,6 +166,7 @@ fn elem_text_standalone() -> Result<(), Error> {
 	xml.elem_text("title", "Hello World")?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<title>Hello World</title>");
 	Ok(())
 }
@@ -110,282 +177,3559 @@ fn elem_text_escapes_content() -> Result<(), Error> {
 	xml.elem_text("msg", "<b>bold</b> & 'quoted'")?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<msg>&lt;b&gt;bold&lt;/b&gt; &amp; &apos;quoted&apos;</msg>");
 	Ok(())
 }
 
 #[test]
 fn elem_text_with_namespace() -> Result<(), Error> {
-	// namespace prefix is applied to opening tag but not the closing tag in elem_text
 	let mut xml = XmlWriter::compact_mode(Vec::new());
 	xml.set_namespace("ns");
 	xml.elem_text("title", "text")?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<ns:title>text</title>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<ns:title>text</ns:title>");
 	Ok(())
 }
 
-// ---- elem_text pretty ----------------------------------------------------------
-
 #[test]
-fn elem_text_pretty() -> Result<(), Error> {
-	// elem_text() self-closes the open parent; the new element gets indentation
+fn elem_text_closing_tag_with_namespace() -> Result<(), Error> {
+	// namespace prefix is applied to both the opening and the closing tag
 	let mut xml = XmlWriter::pretty_mode(Vec::new());
 	xml.begin_elem("root")?;
-	xml.elem_text("title", "Hello")?;
+	xml.empty_elem("first")?;
+	xml.set_namespace("st");
+	xml.elem_text("title", "text")?;
+	xml.unset_namespace();
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root/>\n  <title>Hello</title>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <first/>\n  <st:title>text</st:title>\n</root>");
 	Ok(())
 }
 
-// ---- escape characters ---------------------------------------------------------
-
 #[test]
-fn text_escapes_ampersand() -> Result<(), Error> {
-	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.begin_elem("root")?;
-	xml.text("a & b")?;
+fn elem_text_ns() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_namespace("soap");
+	xml.begin_elem("Body")?;
+	xml.ns_decl(&vec![
+		(Some("soap"), "http://www.w3.org/2003/05/soap-envelope"),
+		(Some("m"), "http://example.com/stock"),
+	])?;
+	xml.elem_text_ns("m", "StockName", "T&T")?;
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root>a &amp; b</root>");
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			r#"<soap:Body xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:m="http://example.com/stock">"#,
+			"\n  <m:StockName>T&amp;T</m:StockName>\n</soap:Body>"
+		)
+	);
 	Ok(())
 }
 
 #[test]
-fn text_escapes_lt_gt() -> Result<(), Error> {
+fn elem_text_ns_invalid_prefix() {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.begin_elem("root")?;
-	xml.text("<value>")?;
+	assert!(matches!(xml.elem_text_ns("m:x", "name", "text"), Err(Error::InvalidName)));
+}
+
+#[test]
+fn elem_text_attrs() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("list")?;
+	xml.elem_text_attrs("item", [("id", "1"), ("class", "a&b")], "<value> & 'more'")?;
+	xml.elem_text_attrs("item", [], "plain")?;
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root>&lt;value&gt;</root>");
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<list>\n",
+			"  <item id=\"1\" class=\"a&amp;b\">&lt;value&gt; &amp; &apos;more&apos;</item>\n",
+			"  <item>plain</item>\n",
+			"</list>"
+		)
+	);
 	Ok(())
 }
 
 #[test]
-fn attr_esc_escapes_backslash_in_name() -> Result<(), Error> {
+fn elem_text_opt_nullable() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.begin_elem("root")?;
-	xml.attr_esc("na\\me", "val")?;
+	xml.begin_elem("record")?;
+	xml.elem_text_opt("some", Some("v & w"), true)?;
+	xml.elem_text_opt("omitted", None, true)?;
+	xml.elem_text_opt("none", None, false)?;
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root na\\\\me=\"val\"/>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<record><some>v &amp; w</some><none/></record>");
 	Ok(())
 }
 
 #[test]
-fn comment_escapes_special_chars() -> Result<(), Error> {
-	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.begin_elem("root")?;
-	xml.comment("a < b & c > d")?;
+fn elem_path_nested() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("settings")?;
+	xml.elem_path("config/server/port", "8080")?;
+	xml.elem_path("name", "a & b")?;
+	assert_eq!(xml.elem_path("config//port", "1"), Err(Error::InvalidName));
+	assert_eq!(xml.elem_path("config/1st", "1"), Err(Error::InvalidName));
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root><!-- a &lt; b &amp; c &gt; d --></root>");
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<settings>\n",
+			"  <config>\n",
+			"    <server>\n",
+			"      <port>8080</port>\n",
+			"    </server>\n",
+			"  </config>\n",
+			"  <name>a &amp; b</name>\n",
+			"</settings>"
+		)
+	);
 	Ok(())
 }
 
-// ---- mode switching ------------------------------------------------------------
-
 #[test]
-fn mode_switch_compact_to_pretty() -> Result<(), Error> {
+fn nested_config_merges_prefixes() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.set_pretty_mode();
-	xml.begin_elem("root")?;
-	xml.begin_elem("child")?;
-	xml.end_elem()?;
+	xml.begin_elem("config")?;
+	xml.nested_config([
+		("server.port", "8080"),
+		("name", "a & b"),
+		("server.host", "localhost"),
+		("server.tls.enabled", "true"),
+	])?;
+	assert_eq!(xml.nested_config([("server..host", "x")]), Err(Error::InvalidName));
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root>\n  <child/>\n</root>");
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<config><name>a &amp; b</name>",
+			"<server><host>localhost</host><port>8080</port><tls><enabled>true</enabled></tls></server>",
+			"</config>"
+		)
+	);
 	Ok(())
 }
 
+// ---- repeat_empty_elem -----------------------------------------------------------
+
 #[test]
-fn mode_switch_pretty_to_compact() -> Result<(), Error> {
+fn repeat_empty_elem_in_pretty_mode() -> Result<(), Error> {
 	let mut xml = XmlWriter::pretty_mode(Vec::new());
-	xml.set_compact_mode();
-	xml.begin_elem("root")?;
-	xml.begin_elem("child")?;
-	xml.end_elem()?;
+	xml.begin_elem("row")?;
+	xml.repeat_empty_elem("cell", 3)?;
+	xml.repeat_empty_elem("none", 0)?;
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root><child/></root>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<row>\n  <cell/>\n  <cell/>\n  <cell/>\n</row>");
 	Ok(())
 }
 
-// ---- namespace getter ----------------------------------------------------------
+// ---- elem_base64 / elem_hex ------------------------------------------------------
 
-#[test]
-fn namespace_getter() {
+/// Text content of `<v>` written by `f`
+fn encoded(f: impl FnOnce(&mut XmlWriter<Vec<u8>>) -> Result<(), Error>) -> Result<String, Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	assert!(xml.namespace().is_none());
-	xml.set_namespace("ns");
-	assert_eq!(xml.namespace(), Some("ns"));
-	xml.unset_namespace();
-	assert!(xml.namespace().is_none());
+	f(&mut xml)?;
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	Ok(res
+		.strip_prefix("<v>")
+		.and_then(|res| res.strip_suffix("</v>"))
+		.unwrap_or(&res)
+		.into())
 }
 
-// ---- into_inner ----------------------------------------------------------------
+#[test]
+fn elem_base64_known_vectors() -> Result<(), Error> {
+	// RFC 4648
+	let vectors = [
+		("", "<v/>"),
+		("f", "Zg=="),
+		("fo", "Zm8="),
+		("foo", "Zm9v"),
+		("foob", "Zm9vYg=="),
+		("fooba", "Zm9vYmE="),
+		("foobar", "Zm9vYmFy"),
+	];
+	for (data, expected) in vectors {
+		assert_eq!(encoded(|xml| xml.elem_base64("v", data.as_bytes()))?, expected);
+	}
+	// chunks of 48 bytes are encoded on their own
+	let data: Vec<u8> = (0..=255).collect();
+	let whole = encoded(|xml| xml.elem_base64("v", &data[..97]))?;
+	let mut parts = String::new();
+	for part in [&data[..48], &data[48..96], &data[96..97]] {
+		parts.push_str(&encoded(|xml| xml.elem_base64("v", part))?);
+	}
+	assert_eq!(whole, parts);
+	assert!(whole.ends_with("YA=="));
+	Ok(())
+}
 
 #[test]
-fn into_inner_returns_buffer() -> Result<(), Error> {
+fn elem_hex_known_vectors() -> Result<(), Error> {
+	assert_eq!(encoded(|xml| xml.elem_hex("v", &[0x00, 0x7f, 0xab, 0xff]))?, "007fabff");
+	let data: Vec<u8> = (0..=255).collect();
+	let expected = data.iter().fold(String::new(), |mut hex, byte| {
+		core::fmt::Write::write_fmt(&mut hex, format_args!("{byte:02x}")).unwrap();
+		hex
+	});
+	assert_eq!(encoded(|xml| xml.elem_hex("v", &data))?, expected);
+	Ok(())
+}
+
+// ---- elem_nil --------------------------------------------------------------------
+
+const XSI: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+#[test]
+fn elem_nil_with_declared_xsi() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_track_namespaces(true);
+	xml.begin_elem("person")?;
+	xml.ns_decl(&vec![(Some("xsi"), XSI)])?;
+	xml.elem_nil("birthday")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		r#"<person xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><birthday xsi:nil="true"/></person>"#
+	);
+	Ok(())
+}
+
+#[test]
+fn elem_nil_with_undeclared_xsi() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
 	xml.begin_elem("root")?;
+	xml.begin_elem("scope")?;
+	xml.ns_decl(&vec![(Some("xsi"), XSI)])?;
 	xml.end_elem()?;
-	let buf = xml.into_inner();
-	assert_eq!(&buf, b"<root/>");
+	// without tracking the declaration is up to the caller
+	xml.elem_nil("unchecked")?;
+	xml.set_track_namespaces(true);
+	assert_eq!(
+		xml.elem_nil("checked"),
+		Err(Error::UndeclaredNamespace { namespace: "xsi".into() })
+	);
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(
+		&res,
+		r#"<root><scope xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"/><unchecked xsi:nil="true"/></root>"#
+	);
 	Ok(())
 }
 
-// ---- debug impl ----------------------------------------------------------------
+// ---- begin_elem / end_elem auto close ------------------------------------------
 
 #[test]
-fn debug_format() -> Result<(), Error> {
-	let mut xml = XmlWriter::compact_mode(Vec::new());
+fn begin_end_elem_with_attributes_only_self_closes() -> Result<(), Error> {
+	let configs = [
+		(Config::compact(), r#"<r><x a="1" b="2"/><x a="1" b="2">t</x></r>"#),
+		(
+			Config::compact().with_sort_attributes(true),
+			r#"<r><x a="1" b="2"/><x a="1" b="2">t</x></r>"#,
+		),
+		(
+			Config::compact().with_ns_decl_order(NsOrder::After),
+			r#"<r><x a="1" b="2"/><x a="1" b="2">t</x></r>"#,
+		),
+		(
+			Config::compact().with_buffer_size(8),
+			r#"<r><x a="1" b="2"/><x a="1" b="2">t</x></r>"#,
+		),
+		(
+			Config::pretty(),
+			"<r>\n  <x a=\"1\" b=\"2\"/>\n  <x a=\"1\" b=\"2\">t</x>\n</r>",
+		),
+		(
+			Config::compact().with_expand_empty_elements(true),
+			r#"<r><x a="1" b="2"></x><x a="1" b="2">t</x></r>"#,
+		),
+	];
+	for (config, expected) in configs {
+		let mut xml = XmlWriter::with_config(Vec::new(), config);
+		xml.begin_elem("r")?;
+		for content in [None, Some("t")] {
+			xml.begin_elem("x")?;
+			xml.attr("a", "1")?;
+			xml.attr("b", "2")?;
+			if let Some(text) = content {
+				xml.text(text)?;
+			}
+			xml.end_elem()?;
+		}
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+	Ok(())
+}
+
+// ---- elem_text pretty ----------------------------------------------------------
+
+#[test]
+fn elem_text_pretty() -> Result<(), Error> {
+	// elem_text() self-closes the open parent; the new element gets indentation
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
 	xml.begin_elem("root")?;
-	let s = format!("{xml:?}");
-	assert!(s.contains("XmlWriter"));
-	assert!(s.contains("root"));
+	xml.elem_text("title", "Hello")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root/>\n  <title>Hello</title>");
 	Ok(())
 }
 
-// ---- close on empty stack ------------------------------------------------------
+// ---- attr_ns -------------------------------------------------------------------
 
 #[test]
-fn close_on_empty_stack() -> Result<(), Error> {
+fn attr_ns_xlink_href() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	// calling close with nothing open is a no-op
-	xml.close()?;
+	xml.begin_elem("use")?;
+	xml.attr_ns("xlink", "href", "#a&b")?;
+	xml.end_elem()?;
+
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<use xlink:href=\"#a&amp;b\"/>");
 	Ok(())
 }
 
-// ---- error cases ---------------------------------------------------------------
-
 #[test]
-fn error_attr_without_element() {
+fn attr_ns_invalid_names() {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	let err = xml.attr("key", "val").unwrap_err();
-	assert!(matches!(err, Error::WriteWithoutElement));
+	xml.begin_elem("use").unwrap();
+	assert!(matches!(xml.attr_ns("x:link", "href", "#a"), Err(Error::InvalidName)));
+	assert!(matches!(xml.attr_ns("xlink", "1href", "#a"), Err(Error::InvalidName)));
+	assert!(matches!(xml.attr_ns("", "href", "#a"), Err(Error::InvalidName)));
 }
 
 #[test]
-fn error_attr_esc_without_element() {
+fn error_attr_ns_without_element() {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	let err = xml.attr_esc("key", "val").unwrap_err();
+	let err = xml.attr_ns("xlink", "href", "#a").unwrap_err();
 	assert!(matches!(err, Error::WriteWithoutElement));
 }
 
 #[test]
-fn error_ns_decl_without_element() {
+fn attr_ns_uri_resolves_registered_prefix() -> Result<(), Error> {
+	const XLINK: &str = "http://www.w3.org/1999/xlink";
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	let nsmap = vec![(None, "http://example.com/")];
-	let err = xml.ns_decl(&nsmap).unwrap_err();
-	assert!(matches!(err, Error::OpenNamespaceWithoutElement));
+	xml.set_track_namespaces(true);
+	xml.register_ns("xl", XLINK)?;
+	xml.register_ns("xlink", XLINK)?;
+	assert_eq!(
+		xml.register_ns("xmlns", "urn:x"),
+		Err(Error::ReservedPrefix { prefix: "xmlns".into() })
+	);
+	xml.begin_elem("svg")?;
+	assert_eq!(
+		xml.attr_ns_uri(XLINK, "href", "#a"),
+		Err(Error::UndeclaredNamespace {
+			namespace: "xlink".into()
+		})
+	);
+	xml.ns_decl(&vec![(Some("xlink"), XLINK)])?;
+	xml.begin_elem("use")?;
+	xml.attr_ns_uri(XLINK, "href", "#a&b")?;
+	assert_eq!(
+		xml.attr_ns_uri("urn:unknown", "href", "#a"),
+		Err(Error::UndeclaredNamespace {
+			namespace: "urn:unknown".into()
+		})
+	);
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><use xlink:href="#a&amp;b"/></svg>"##
+	);
+	Ok(())
 }
 
+// ---- attr_bare -----------------------------------------------------------------
+
 #[test]
-fn error_end_elem_without_open() {
+fn attr_bare_html_boolean_attribute() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	let err = xml.end_elem().unwrap_err();
-	assert!(matches!(err, Error::CloseElement));
+	xml.begin_elem("input")?;
+	xml.attr_bare("disabled")?;
+	xml.end_elem()?;
+	assert_eq!(xml.attr_bare("checked"), Err(Error::WriteWithoutElement));
+
+	// not well-formed XML, but valid HTML
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<input disabled/>");
+	Ok(())
 }
 
-// ---- nested namespaces ---------------------------------------------------------
+// ---- attr_raw ------------------------------------------------------------------
 
 #[test]
-fn nested_namespace_change() -> Result<(), Error> {
+fn attr_raw_pre_escaped_value() -> Result<(), Error> {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
-	xml.set_namespace("a");
-	xml.begin_elem("outer")?;
-	xml.set_namespace("b");
-	xml.begin_elem("inner")?;
-	xml.end_elem()?;
+	xml.begin_elem("a")?;
+	xml.attr_raw("title", "Tom &amp; Jerry")?;
+	xml.attr_esc("alt", "Tom & Jerry")?;
 	xml.end_elem()?;
+	assert_eq!(xml.attr_raw("href", "x"), Err(Error::WriteWithoutElement));
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<a:outer><b:inner/></a:outer>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<a title=\"Tom &amp; Jerry\" alt=\"Tom &amp; Jerry\"/>");
 	Ok(())
 }
 
-// ---- empty_elem without namespace ----------------------------------------------
+// ---- attr_streamed --------------------------------------------------------------
+
+/// Returns a chunk reader handing out `value` in chunks of `size` bytes
+fn chunks(value: &str, size: usize) -> impl FnMut(&mut [u8]) -> Result<usize, Error> + '_ {
+	let mut rest = value.as_bytes();
+	move |buf: &mut [u8]| {
+		let len = size.min(buf.len()).min(rest.len());
+		buf[..len].copy_from_slice(&rest[..len]);
+		rest = &rest[len..];
+		Ok(len)
+	}
+}
 
 #[test]
-fn empty_elem_no_namespace() -> Result<(), Error> {
+fn attr_streamed_matches_attr_esc() -> Result<(), Error> {
+	let value = "a \"quoted\" <value> & 'more'\r\nü\r\r\n end";
+	for normalize in [false, true] {
+		let mut expected = XmlWriter::compact_mode(Vec::new());
+		expected.set_normalize_newlines(normalize);
+		expected.begin_elem("root")?;
+		expected.attr_esc("data", value)?;
+		expected.end_elem()?;
+		let expected = String::try_from(expected).unwrap();
+
+		for size in [1, 2, 3, 7, 1000] {
+			let mut xml = XmlWriter::compact_mode(Vec::new());
+			xml.set_normalize_newlines(normalize);
+			xml.begin_elem("root")?;
+			xml.attr_streamed("data", chunks(value, size))?;
+			xml.end_elem()?;
+
+			let res = String::try_from(xml).unwrap();
+			assert_well_formed(&res);
+			assert_eq!(res, expected);
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn attr_streamed_without_element() {
 	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(matches!(
+		xml.attr_streamed("data", chunks("value", 2)),
+		Err(Error::WriteWithoutElement)
+	));
+}
+
+// ---- attribute sanitizer -------------------------------------------------------
+
+const fn reject_long(value: &str) -> Result<(), Error> {
+	if value.len() > 8 {
+		Err(Error::RejectedAttrValue)
+	} else {
+		Ok(())
+	}
+}
+
+#[test]
+fn attr_sanitizer_rejects_long_values() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_attr_sanitizer(reject_long);
 	xml.begin_elem("root")?;
-	xml.empty_elem("leaf")?;
+	xml.attr("short", "12345678")?;
+	assert!(matches!(xml.attr("long", "123456789"), Err(Error::RejectedAttrValue)));
+	assert!(matches!(xml.attr_esc("long", "123456789"), Err(Error::RejectedAttrValue)));
+	xml.unset_attr_sanitizer();
+	xml.attr("long", "123456789")?;
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root><leaf/></root>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root short=\"12345678\" long=\"123456789\"/>");
 	Ok(())
 }
 
-// ---- pretty indentation details ------------------------------------------------
-
 #[test]
-fn pretty_deep_nesting() -> Result<(), Error> {
-	let mut xml = XmlWriter::pretty_mode(Vec::new());
-	xml.begin_elem("a")?;
-	xml.begin_elem("b")?;
-	xml.begin_elem("c")?;
-	xml.text("leaf")?;
-	xml.end_elem()?;
-	xml.end_elem()?;
+fn attr_sanitizer_rejects_elem_attrs_up_front() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_attr_sanitizer(reject_long);
+	xml.begin_elem("root")?;
+	assert_eq!(
+		xml.empty_elem_ns_attrs(Some("svg"), "use", &[("a", "1"), ("long", "123456789")]),
+		Err(Error::RejectedAttrValue)
+	);
+	assert_eq!(
+		xml.elem_text_attrs("item", [("a", "1"), ("long", "123456789")], "text"),
+		Err(Error::RejectedAttrValue)
+	);
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<a>\n  <b>\n    <c>leaf</c>\n  </b>\n</a>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root/>");
 	Ok(())
 }
 
 #[test]
-fn pretty_empty_elem_no_namespace() -> Result<(), Error> {
-	let mut xml = XmlWriter::pretty_mode(Vec::new());
+fn max_attr_value_len_boundary() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_max_attr_value_len(4);
 	xml.begin_elem("root")?;
-	xml.empty_elem("br")?;
+	xml.attr("a", "1234")?;
+	xml.attr_esc("b", "<<<<")?;
+	assert_eq!(xml.attr("c", "12345"), Err(Error::AttrValueTooLong { len: 5, max: 4 }));
+	assert_eq!(xml.attr_esc("c", "12345"), Err(Error::AttrValueTooLong { len: 5, max: 4 }));
 	xml.end_elem()?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root>\n  <br/>\n</root>");
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root a=\"1234\" b=\"&lt;&lt;&lt;&lt;\"/>");
 	Ok(())
 }
 
-// ---- close() with open elements ------------------------------------------------
+#[test]
+fn max_attr_value_len_streamed() -> Result<(), Error> {
+	for (sort, expected) in [(false, "<root v=\"abc\"/>"), (true, "<root/>")] {
+		let mut xml = XmlWriter::compact_mode(Vec::new());
+		xml.set_max_attr_value_len(4);
+		xml.set_sort_attributes(sort);
+		xml.begin_elem("root")?;
+		assert_eq!(
+			xml.attr_streamed("v", chunks("abcdef", 3)),
+			Err(Error::AttrValueTooLong { len: 6, max: 4 })
+		);
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+	Ok(())
+}
+
+// ---- element decorator -----------------------------------------------------------
 
 #[test]
-fn close_with_open_elements() -> Result<(), Error> {
-	// close() must auto-close all open elements without explicit end_elem() calls
+fn element_decorator_adds_idx() -> Result<(), Error> {
+	static IDX: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+	fn idx(xml: &mut XmlWriter<'_, Vec<u8>>, _name: &str) -> Result<(), Error> {
+		let idx = IDX.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+		xml.attr("idx", &format!("{idx}"))
+	}
+
 	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_element_decorator(idx);
 	xml.begin_elem("root")?;
+	xml.attr("a", "b")?;
+	xml.empty_elem("empty")?;
 	xml.begin_elem("child")?;
-	xml.close()?;
+	xml.text("text")?;
+	xml.end_elem()?;
+	xml.unset_element_decorator();
+	xml.empty_elem("plain")?;
+	xml.end_elem()?;
+	xml.set_element_decorator(idx);
+	xml.elem("leaf")?;
 
 	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root><child/></root>");
+	assert_eq!(
+		&res,
+		"<root idx=\"0\" a=\"b\"><empty idx=\"1\"/><child idx=\"2\">text</child><plain/></root><leaf idx=\"3\"/>"
+	);
 	Ok(())
 }
 
 #[test]
-fn close_with_open_elements_pretty() -> Result<(), Error> {
-	let mut xml = XmlWriter::pretty_mode(Vec::new());
-	xml.begin_elem("root")?;
-	xml.begin_elem("child")?;
-	xml.begin_elem("leaf")?;
-	xml.close()?;
+fn element_decorator_writes_attributes_only() {
+	fn nested(xml: &mut XmlWriter<'_, Vec<u8>>, _name: &str) -> Result<(), Error> {
+		xml.begin_elem("nested")
+	}
+	fn text(xml: &mut XmlWriter<'_, Vec<u8>>, _name: &str) -> Result<(), Error> {
+		xml.text("text")
+	}
 
-	let res = String::try_from(xml).unwrap();
-	assert_eq!(&res, "<root>\n  <child>\n    <leaf/>\n  </child>\n</root>");
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_element_decorator(nested);
+	// the decorator is not called for the element it writes itself
+	assert_eq!(xml.begin_elem("root"), Err(Error::DecoratorWroteContent));
+	xml.set_element_decorator(text);
+	assert_eq!(xml.empty_elem("empty"), Err(Error::DecoratorWroteContent));
+}
+
+#[test]
+fn element_decorator_error_keeps_output_well_formed() -> Result<(), Error> {
+	fn failing(xml: &mut XmlWriter<'_, Vec<u8>>, name: &str) -> Result<(), Error> {
+		xml.attr("a", "1")?;
+		if name == "bad" {
+			return Err(Error::RejectedAttrValue);
+		}
+		Ok(())
+	}
+
+	for order in [NsOrder::AsWritten, NsOrder::Before] {
+		let mut xml = XmlWriter::compact_mode(Vec::new());
+		xml.set_ns_decl_order(order);
+		xml.set_element_decorator(failing);
+		xml.begin_elem("root")?;
+		assert_eq!(xml.empty_elem("bad"), Err(Error::RejectedAttrValue));
+		xml.text("t")?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, "<root a=\"1\"><bad a=\"1\"/>t</root>");
+	}
+	Ok(())
+}
+
+// ---- write observer ----------------------------------------------------------------
+
+#[cfg(feature = "std")]
+static OBSERVED: std::sync::Mutex<Vec<u8>> = std::sync::Mutex::new(Vec::new());
+
+#[cfg(feature = "std")]
+fn observe(bytes: &[u8]) {
+	OBSERVED.lock().unwrap().extend_from_slice(bytes);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn write_observer_sees_buffer_content() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_write_observer(observe);
+	xml.set_sort_attributes(true);
+	xml.begin_elem("root")?;
+	xml.attr("b", "1")?;
+	xml.attr("a", "2")?;
+	xml.begin_elem("child")?;
+	xml.text("x < y")?;
+	xml.close()?;
+	xml.unset_write_observer();
+	xml.write("<!-- unobserved -->")?;
+
+	let res = String::try_from(xml).unwrap();
+	let observed = String::from_utf8(OBSERVED.lock().unwrap().clone()).unwrap();
+	assert_eq!(format!("{observed}<!-- unobserved -->"), res);
+	assert_eq!(&observed, "<root a=\"2\" b=\"1\">\n  <child>x &lt; y</child>\n</root>");
+	Ok(())
+}
+
+// ---- close observer ----------------------------------------------------------------
+
+#[cfg(feature = "std")]
+static CLOSED: std::sync::Mutex<Vec<(String, usize)>> = std::sync::Mutex::new(Vec::new());
+
+#[cfg(feature = "std")]
+fn observe_close(name: &str, depth: usize) {
+	CLOSED.lock().unwrap().push((name.into(), depth));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn close_observer_counts_closed_elements() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_close_observer(observe_close);
+	xml.begin_elem("log")?;
+	for _ in 0..3 {
+		xml.begin_elem("record")?;
+		xml.begin_elem("field")?;
+		xml.text("x")?;
+		xml.end_elem()?;
+		xml.empty_elem("flag")?;
+		xml.end_elem()?;
+	}
+	// elements written as a whole
+	xml.elem_text("note", "x")?;
+	xml.elem_text_attrs("item", [("n", "1")], "x")?;
+	xml.begin_elem("trailer")?;
+	xml.close()?;
+	xml.unset_close_observer();
+	xml.begin_elem("unobserved")?;
+	xml.end_elem()?;
+
+	let closed = CLOSED.lock().unwrap().clone();
+	assert_eq!(closed.len(), 13);
+	assert_eq!(
+		closed
+			.iter()
+			.filter(|(name, _)| name == "record")
+			.count(),
+		3
+	);
+	assert_eq!(
+		closed[..3],
+		[
+			("field".into(), 2),
+			("flag".into(), 2),
+			("record".into(), 1)
+		]
+	);
+	assert_eq!(
+		closed[9..],
+		[
+			("note".into(), 1),
+			("item".into(), 1),
+			("trailer".into(), 1),
+			("log".into(), 0)
+		]
+	);
+	Ok(())
+}
+
+// ---- text_fmt ----------------------------------------------------------------------
+
+#[test]
+fn text_fmt_escapes_formatted_output() -> Result<(), Error> {
+	let count = 42;
+	let name = "<b> & 'c'";
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.text_fmt(format_args!("count: {count} & {name}"))?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>count: 42 &amp; &lt;b&gt; &amp; &apos;c&apos;</root>");
+	Ok(())
+}
+
+// ---- text_with ---------------------------------------------------------------------
+
+#[test]
+fn text_with_escapes_each_piece() -> Result<(), Error> {
+	let items = ["<a>", "b & c", "'d'"];
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.text_with(|push| items.iter().try_for_each(|item| push(item)))?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>&lt;a&gt;b &amp; c&apos;d&apos;</root>");
+	Ok(())
+}
+
+#[test]
+fn text_pieces_normalize_split_crlf() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_normalize_newlines(true);
+	xml.begin_elem("root")?;
+	xml.text_with(|push| {
+		push("a\r")?;
+		push("")?;
+		push("\nb\r")?;
+		push("c\r")
+	})?;
+	xml.end_elem()?;
+	xml.begin_elem("fmt")?;
+	xml.text_fmt(format_args!("{}{}", "a\r", "\nb"))?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root>a\nb\nc\n</root><fmt>a\nb</fmt>");
+	Ok(())
+}
+
+// ---- text separator ----------------------------------------------------------------
+
+#[test]
+fn text_separator_between_adjacent_text() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_text_separator(" ");
+	xml.begin_elem("p")?;
+	xml.text("first")?;
+	xml.text_fmt(format_args!("{}", 2))?;
+	xml.empty_elem("br")?;
+	xml.text("third")?;
+	xml.end_elem()?;
+	xml.begin_elem("q")?;
+	xml.text("fourth")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&format!("<r>{res}</r>"));
+	assert_eq!(&res, "<p>first 2<br/>third</p><q>fourth</q>");
+	Ok(())
+}
+
+// ---- empty text --------------------------------------------------------------------
+
+#[test]
+fn empty_text_keeps_self_closing() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_record_events(true);
+	xml.begin_elem("root")?;
+	xml.begin_elem("x")?;
+	xml.text("")?;
+	xml.end_elem()?;
+	xml.set_empty_text_is_noop(false);
+	xml.begin_elem("y")?;
+	xml.text("")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	// only the written empty text is recorded
+	let texts = xml
+		.take_events()
+		.into_iter()
+		.filter(|event| matches!(event, Event::Text(_)))
+		.count();
+	assert_eq!(texts, 1);
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <x/>\n  <y></y>\n</root>");
+	Ok(())
+}
+
+// ---- verbatim ----------------------------------------------------------------------
+
+#[test]
+fn verbatim_in_pretty_mode() -> Result<(), Error> {
+	let code = "fn main() {\n    if a < b && c > d {\n        x[y[0]]> 1;\n    }\n}\n";
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("doc")?;
+	xml.begin_elem("listing")?;
+	xml.verbatim(code)?;
+	xml.empty_elem("end")?;
+	xml.end_elem()?;
+	xml.empty_elem("next")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<doc>\n",
+			"  <listing>fn main() {\n    if a &lt; b &amp;&amp; c > d {\n        x[y[0]]&gt; 1;\n    }\n}\n",
+			"<end/></listing>\n",
+			"  <next/>\n",
+			"</doc>"
+		)
+	);
+	Ok(())
+}
+
+// ---- inline_elem -----------------------------------------------------------------
+
+#[test]
+fn inline_elem_in_pretty_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.inline_elem("p")?;
+	xml.text("Hello ")?;
+	xml.begin_elem("b")?;
+	xml.text("world")?;
+	xml.end_elem()?;
+	xml.text("!")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<p>Hello <b>world</b>!</p>");
+
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("body")?;
+	xml.inline_elem("p")?;
+	xml.empty_elem("br")?;
+	xml.inline_elem("i")?;
+	xml.text("x")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	xml.empty_elem("hr")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<body>\n  <p><br/><i>x</i></p>\n  <hr/>\n</body>");
+	Ok(())
+}
+
+// ---- suppress_next_indent ---------------------------------------------------------
+
+#[test]
+fn suppress_next_indent_once() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("list")?;
+	xml.begin_elem("item")?;
+	xml.text("label")?;
+	xml.suppress_next_indent();
+	xml.empty_elem("inline")?;
+	xml.empty_elem("next")?;
+	xml.end_elem()?;
+	xml.empty_elem("item")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<list>\n",
+			"  <item>label<inline/>\n",
+			"    <next/>\n",
+			"  </item>\n",
+			"  <item/>\n",
+			"</list>"
+		)
+	);
+	Ok(())
+}
+
+// ---- append_child_document ---------------------------------------------------------
+
+#[test]
+fn append_child_document_fragment() -> Result<(), Error> {
+	let mut section = XmlWriter::pretty_mode(Vec::new());
+	section.begin_elem("a")?;
+	section.empty_elem("b")?;
+	section.end_elem()?;
+	section.empty_elem("c")?;
+	let section = section.into_inner();
+
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("doc")?;
+	xml.begin_elem("section")?;
+	xml.append_child_document(&section)?;
+	xml.end_elem()?;
+	xml.append_child_document(&section)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<doc>\n",
+			"  <section>\n",
+			"    <a>\n",
+			"      <b/>\n",
+			"    </a>\n",
+			"    <c/>\n",
+			"  </section>\n",
+			"  <a>\n",
+			"    <b/>\n",
+			"  </a>\n",
+			"  <c/>\n",
+			"</doc>"
+		)
+	);
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("doc")?;
+	xml.append_child_document(b"<a><b/></a><c/>")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<doc><a><b/></a><c/></doc>");
+
+	let config = Config::pretty().with_line_ending("\r\n");
+	let mut xml = XmlWriter::with_config(Vec::new(), config);
+	xml.begin_elem("doc")?;
+	xml.append_child_document(&section)?;
+	xml.append_child_document(b"<d/>\r\n<e/>\r\n")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<doc>\r\n  <a>\r\n    <b/>\r\n  </a>\r\n  <c/>\r\n  <d/>\r\n  <e/>\r\n</doc>"
+	);
+	Ok(())
+}
+
+// ---- cdata_inline ----------------------------------------------------------------
+
+#[test]
+fn cdata_inline_in_pretty_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("doc")?;
+	xml.begin_elem("script")?;
+	xml.cdata("a < b")?;
+	xml.end_elem()?;
+	xml.begin_elem("script")?;
+	xml.cdata_inline("a < b")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<doc>\n",
+			"  <script>\n",
+			"    <![CDATA[a < b]]>\n",
+			"  </script>\n",
+			"  <script><![CDATA[a < b]]></script>\n",
+			"</doc>"
+		)
+	);
+	Ok(())
+}
+
+#[test]
+fn cdata_bytes_splits_end_marker() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("payload")?;
+	xml.cdata_bytes(b"a]]>b]]]>c")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<payload><![CDATA[a]]]]><![CDATA[>b]]]]]><![CDATA[>c]]></payload>");
+	Ok(())
+}
+
+#[test]
+fn cdata_unsafe_policies() -> Result<(), Error> {
+	let expected = [
+		(CdataPolicy::Split, "<p><![CDATA[a]]]]><![CDATA[>b]]><![CDATA[c]]></p>"),
+		(CdataPolicy::EscapeAsText, "<p>a]]&gt;b<![CDATA[c]]></p>"),
+	];
+	for (policy, expected) in expected {
+		let mut xml = XmlWriter::compact_mode(Vec::new());
+		xml.set_cdata_unsafe_policy(policy);
+		xml.begin_elem("p")?;
+		xml.cdata("a]]>b")?;
+		xml.cdata_inline("c")?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_cdata_unsafe_policy(CdataPolicy::Error);
+	xml.begin_elem("p")?;
+	assert_eq!(xml.cdata("a]]>b"), Err(Error::InvalidCdata));
+	assert_eq!(xml.cdata_inline("a]]>b"), Err(Error::InvalidCdata));
+	assert_eq!(xml.cdata_bytes(b"a]]>b"), Err(Error::InvalidCdata));
+	xml.cdata("a]]b")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<p><![CDATA[a]]b]]></p>");
+	Ok(())
+}
+
+// ---- escape characters ---------------------------------------------------------
+
+#[test]
+fn text_escapes_ampersand() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.text("a & b")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>a &amp; b</root>");
+	Ok(())
+}
+
+#[test]
+fn text_escapes_lt_gt() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.text("<value>")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>&lt;value&gt;</root>");
+	Ok(())
+}
+
+#[test]
+fn attr_esc_escapes_backslash_in_name() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.attr_esc("na\\me", "val")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root na\\\\me=\"val\"/>");
+	Ok(())
+}
+
+#[test]
+fn attr_min_esc_vs_attr_esc() -> Result<(), Error> {
+	let value = "a<b>c \"d\" 'e' & f";
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.attr_esc("esc", value)?;
+	xml.attr_min_esc("min", value)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root esc=\"a&lt;b&gt;c &quot;d&quot; &apos;e&apos; &amp; f\" min=\"a&lt;b>c &quot;d&quot; 'e' &amp; f\"/>"
+	);
+	Ok(())
+}
+
+#[test]
+fn raw_entity_in_text() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("p")?;
+	xml.text("a")?;
+	xml.raw_entity("nbsp")?;
+	xml.text("& b")?;
+	assert_eq!(xml.raw_entity("no space"), Err(Error::InvalidName));
+	assert_eq!(xml.raw_entity("&amp;"), Err(Error::InvalidName));
+	xml.end_elem()?;
+
+	// `nbsp` is not declared, so the output is not checked for well-formedness
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<p>a&nbsp;&amp; b</p>");
+	Ok(())
+}
+
+#[test]
+fn char_ref_in_text() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("p")?;
+	xml.char_ref(0xA0)?;
+	xml.char_ref(0x1F600)?;
+	xml.char_ref_hex(0xA0)?;
+	xml.char_ref_hex(0x1F600)?;
+	// the shortest and longest references
+	xml.char_ref(9)?;
+	xml.char_ref_hex(9)?;
+	xml.char_ref(0x10_FFFF)?;
+	xml.char_ref_hex(0x10_FFFF)?;
+	assert_eq!(xml.char_ref(0), Err(Error::InvalidCharacter));
+	assert_eq!(xml.char_ref_hex(0xD800), Err(Error::InvalidCharacter));
+	assert_eq!(xml.char_ref(0x11_0000), Err(Error::InvalidCharacter));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<p>&#160;&#128512;&#xA0;&#x1F600;&#9;&#x9;&#1114111;&#x10FFFF;</p>");
+	Ok(())
+}
+
+#[test]
+fn apostrophe_escape_styles() -> Result<(), Error> {
+	for (style, expected) in [
+		(ApostropheStyle::Apos, "<p title=\"it&apos;s\">it&apos;s</p>"),
+		(ApostropheStyle::NumericDec, "<p title=\"it&#39;s\">it&#39;s</p>"),
+		(ApostropheStyle::None, "<p title=\"it's\">it's</p>"),
+	] {
+		let mut xml = XmlWriter::compact_mode(Vec::new());
+		xml.set_apostrophe_escape(style);
+		xml.begin_elem("p")?;
+		xml.attr_esc("title", "it's")?;
+		xml.text("it's")?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+
+	// a literal apostrophe would end a single quoted value
+	let mut xml = XmlWriter::with_config(
+		Vec::new(),
+		Config::compact()
+			.with_quote(Quote::Single)
+			.with_apostrophe_escape(ApostropheStyle::None),
+	);
+	xml.begin_elem("p")?;
+	xml.attr_esc("title", "it's")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<p title='it&#39;s'/>");
+	Ok(())
+}
+
+#[test]
+fn normalize_newlines_in_text() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.text("a\r\nb\rc\nd")?;
+	xml.set_normalize_newlines(true);
+	xml.text("|a\r\nb\rc\nd\r")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>a\r\nb\rc\nd|a\nb\nc\nd\n</root>");
+	Ok(())
+}
+
+#[test]
+fn normalize_newlines_in_attributes() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_normalize_newlines(true);
+	xml.begin_elem("root")?;
+	xml.attr_esc("a", "1\r\n2\r3\n4")?;
+	xml.attr_min_esc("b", "1\r\n2")?;
+	// names and unescaped values are left untouched
+	xml.attr("c", "1\r\n2")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root a=\"1&#10;2&#10;3&#10;4\" b=\"1&#10;2\" c=\"1\r\n2\"/>");
+	Ok(())
+}
+
+#[test]
+fn comment_escapes_special_chars() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.comment("a < b & c > d")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><!-- a &lt; b &amp; c &gt; d --></root>");
+	Ok(())
+}
+
+#[test]
+fn strict_escaping_vs_default() -> Result<(), Error> {
+	let text = "a < b > c & \"d\" 'e' ]]> f";
+	for (strict, expected) in [
+		(
+			false,
+			concat!(
+				"<root a=\"&lt;&gt; &amp; &quot;x&quot; &apos;y&apos;\">",
+				"a &lt; b &gt; c &amp; &quot;d&quot; &apos;e&apos; ]]&gt; f</root>"
+			),
+		),
+		(
+			true,
+			concat!(
+				"<root a=\"&lt;> &amp; &quot;x&quot; 'y'\">",
+				"a &lt; b > c &amp; \"d\" 'e' ]]&gt; f</root>"
+			),
+		),
+	] {
+		let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_strict_escaping(strict));
+		xml.begin_elem("root")?;
+		xml.attr_esc("a", "<> & \"x\" 'y'")?;
+		xml.text(text)?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+	Ok(())
+}
+
+#[test]
+fn strict_escaping_quotes_and_split_cdata_end() -> Result<(), Error> {
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_quote(Quote::Single));
+	xml.set_strict_escaping(true);
+	xml.begin_elem("root")?;
+	xml.attr_esc("a", "\"x\" 'y'")?;
+	xml.text("]]")?;
+	xml.text(">")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root a='\"x\" &apos;y&apos;'>]]&gt;</root>");
+	Ok(())
+}
+
+// ---- marker_comment ------------------------------------------------------------
+
+#[test]
+fn marker_comment() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.marker_comment("region: <generated>")?;
+	xml.elem("child")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <!-- region: <generated> -->\n  <child/>\n</root>");
+	Ok(())
+}
+
+#[test]
+fn marker_comment_rejects_invalid_text() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	assert!(matches!(xml.marker_comment("region:\ngenerated"), Err(Error::InvalidComment)));
+	assert!(matches!(
+		xml.marker_comment("region:\r\ngenerated"),
+		Err(Error::InvalidComment)
+	));
+	assert!(matches!(xml.marker_comment("region--generated"), Err(Error::InvalidComment)));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root/>");
+	Ok(())
+}
+
+#[test]
+fn comment_raw() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.comment_raw("x &amp; y\n& z")?;
+	assert!(matches!(xml.comment_raw("x -- y"), Err(Error::InvalidComment)));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><!-- x &amp; y\n& z --></root>");
+	Ok(())
+}
+
+#[test]
+fn provenance_comment() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.dtd("UTF-8")?;
+	xml.provenance_comment("tool", "1.2.3", Some("2025-01-01"))?;
+	xml.provenance_comment("other", "0.1", None)?;
+	assert_eq!(xml.provenance_comment("bad--tool", "1.0", None), Err(Error::InvalidComment));
+	assert_eq!(
+		xml.provenance_comment("tool", "1.0", Some("2025--01")),
+		Err(Error::InvalidComment)
+	);
+	xml.empty_elem("root")?;
+	assert_eq!(xml.provenance_comment("tool", "1.0", None), Err(Error::ContentAlreadyWritten));
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n",
+			"<!-- generated by tool v1.2.3 at 2025-01-01 -->\n",
+			"<!-- generated by other v0.1 -->\n",
+			"<root/>"
+		)
+	);
+	Ok(())
+}
+
+// ---- canonical mode --------------------------------------------------------------
+
+#[test]
+fn canonical_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::canonical_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.attr_esc("b", "'1' > \"0\"\t\n\r")?;
+	xml.attr_ns("x", "c", "2")?;
+	xml.attr("a", "1")?;
+	xml.ns_decl(&vec![
+		(Some("x"), "http://example.com/x"),
+		(None, "http://example.com"),
+	])?;
+	xml.empty_elem("empty")?;
+	xml.begin_elem("also_empty")?;
+	xml.end_elem()?;
+	xml.begin_elem("content")?;
+	xml.text("\"a\" < 'b' & c > d\r")?;
+	xml.cdata(" <e> ")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			r#"<root xmlns="http://example.com" xmlns:x="http://example.com/x" a="1" b="'1' > &quot;0&quot;&#x9;&#xA;&#xD;" x:c="2">"#,
+			"<empty></empty><also_empty></also_empty>",
+			r#"<content>"a" &lt; 'b' &amp; c &gt; d&#xD; &lt;e&gt; </content></root>"#
+		)
+	);
+	Ok(())
+}
+
+#[test]
+fn sort_attributes_in_pretty_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_sort_attributes(true);
+	xml.begin_elem("root")?;
+	xml.attr("z", "1")?;
+	xml.attr("m", "2")?;
+	xml.begin_elem("child")?;
+	xml.attr("b", "3")?;
+	xml.attr("a", "4")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root m=\"2\" z=\"1\">\n  <child a=\"4\" b=\"3\"/>\n</root>");
+	Ok(())
+}
+
+#[test]
+fn sort_attributes_with_ordered_elem() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_sort_attributes(true);
+	xml.begin_elem("root")?;
+	xml.attr("b", "1")?;
+	xml.attr("a", "2")?;
+	xml.begin_elem_ordered("ordered")?;
+	xml.attr("z", "3")?;
+	xml.attr("y", "4")?;
+	xml.begin_elem("sorted")?;
+	xml.attr("d", "5")?;
+	xml.attr("c", "6")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		r#"<root a="2" b="1"><ordered z="3" y="4"><sorted c="6" d="5"/></ordered></root>"#
+	);
+	Ok(())
+}
+
+#[test]
+fn sort_attributes_with_rollback() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_sort_attributes(true);
+	xml.begin_elem("root")?;
+	xml.attr("b", "1")?;
+	let checkpoint = xml.checkpoint();
+	xml.attr("a", "2")?;
+	xml.rollback(checkpoint)?;
+	let staged = xml.checkpoint();
+	xml.end_elem()?;
+	// the staged attributes are gone with their elem
+	assert_eq!(xml.rollback(staged), Err(Error::InvalidCheckpoint));
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root b=\"1\"/>");
+	Ok(())
+}
+
+#[test]
+fn ns_decl_after_attr_comes_first() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_ns_decl_order(NsOrder::Before);
+	xml.begin_elem("root")?;
+	xml.attr("id", "1")?;
+	xml.ns_decl(&vec![(None, "urn:default"), (Some("x"), "urn:x")])?;
+	xml.attr_ns("x", "a", "2")?;
+	let checkpoint = xml.checkpoint();
+	xml.begin_elem("child")?;
+	xml.attr("id", "3")?;
+	xml.ns_decl(&vec![(Some("z"), "urn:z")])?;
+	xml.empty_elem("grandchild")?;
+	xml.end_elem()?;
+	// the flushed attributes are restored by a rollback
+	xml.rollback(checkpoint)?;
+	xml.begin_elem_ordered("ordered")?;
+	xml.attr("id", "2")?;
+	xml.ns_decl(&vec![(Some("y"), "urn:y")])?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		r#"<root xmlns="urn:default" xmlns:x="urn:x" id="1" x:a="2"><ordered id="2" xmlns:y="urn:y"/></root>"#
+	);
+	Ok(())
+}
+
+#[test]
+fn ns_decl_order_rollback_after_close() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_ns_decl_order(NsOrder::Before);
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.attr("id", "1")?;
+	let within = xml.checkpoint();
+	xml.ns_decl(&vec![(Some("x"), "urn:x")])?;
+	xml.end_elem()?;
+	// the collected attributes of child are gone with the elem
+	assert_eq!(xml.rollback(within), Err(Error::InvalidCheckpoint));
+	let after = xml.checkpoint();
+	xml.empty_elem("other")?;
+	xml.rollback(after)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, r#"<root><child xmlns:x="urn:x" id="1"/></root>"#);
+	Ok(())
+}
+
+#[test]
+fn ns_decl_order_modes() -> Result<(), Error> {
+	let expected = [
+		(NsOrder::AsWritten, r#"<root b="1" xmlns:x="urn:x" a="2"/>"#),
+		(NsOrder::Before, r#"<root xmlns:x="urn:x" b="1" a="2"/>"#),
+		(NsOrder::After, r#"<root b="1" a="2" xmlns:x="urn:x"/>"#),
+	];
+	for (order, expected) in expected {
+		let mut xml = XmlWriter::compact_mode(Vec::new());
+		xml.set_ns_decl_order(order);
+		xml.begin_elem("root")?;
+		xml.attr("b", "1")?;
+		xml.ns_decl(&vec![(Some("x"), "urn:x")])?;
+		xml.attr("a", "2")?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+
+	// with sorted attributes
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_sort_attributes(true);
+	xml.set_ns_decl_order(NsOrder::After);
+	xml.begin_elem("root")?;
+	xml.attr("b", "1")?;
+	xml.ns_decl(&vec![(Some("x"), "urn:x")])?;
+	xml.attr("a", "2")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, r#"<root a="2" b="1" xmlns:x="urn:x"/>"#);
+	Ok(())
+}
+
+// ---- config ----------------------------------------------------------------------
+
+#[test]
+fn config_cloned_for_several_writers() -> Result<(), Error> {
+	let config = Config::pretty()
+		.with_indent("\t")
+		.with_line_ending("\r\n")
+		.with_quote(Quote::Single);
+	let first = XmlWriter::with_config(Vec::new(), config);
+	// a writer's config can be used as a template as well
+	let second = XmlWriter::with_config(Vec::new(), first.config().clone());
+	for mut xml in [first, second] {
+		xml.begin_elem("root")?;
+		xml.attr_esc("a", "'1' \"2\"")?;
+		xml.attr_min_esc("b", "'1' \"2\"")?;
+		xml.empty_elem("child")?;
+		xml.end_elem()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(
+			&res,
+			"<root a='&apos;1&apos; &quot;2&quot;' b='&apos;1&apos; \"2\"'>\r\n\t<child/>\r\n</root>"
+		);
+	}
+	Ok(())
+}
+
+#[test]
+fn config_default_is_compact() -> Result<(), Error> {
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::default());
+	xml.begin_elem("root")?;
+	xml.empty_elem("child")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root><child/></root>");
+	Ok(())
+}
+
+#[test]
+fn indent_units() -> Result<(), Error> {
+	for (unit, expected) in [
+		(IndentUnit::Spaces(4), "<a>\n    <b>\n        <c/>\n    </b>\n</a>"),
+		(IndentUnit::Tab, "<a>\n\t<b>\n\t\t<c/>\n\t</b>\n</a>"),
+		(IndentUnit::default(), "<a>\n  <b>\n    <c/>\n  </b>\n</a>"),
+		(IndentUnit::Spaces(0), "<a>\n<b>\n<c/>\n</b>\n</a>"),
+	] {
+		let mut xml = XmlWriter::pretty_mode(Vec::new());
+		xml.set_indent(unit);
+		xml.begin_elem("a")?;
+		xml.begin_elem("b")?;
+		xml.empty_elem("c")?;
+		xml.close()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		assert_eq!(&res, expected);
+	}
+	Ok(())
+}
+
+// ---- final newline ---------------------------------------------------------------
+
+#[test]
+fn final_newline_in_pretty_mode() -> Result<(), Error> {
+	for enabled in [false, true] {
+		let mut xml = XmlWriter::pretty_mode(Vec::new());
+		xml.set_final_newline(enabled);
+		xml.begin_elem("root")?;
+		xml.empty_elem("child")?;
+		xml.close()?;
+
+		let res = String::try_from(xml).unwrap();
+		assert_well_formed(&res);
+		let expected = "<root>\n  <child/>\n</root>";
+		if enabled {
+			assert_eq!(res, [expected, "\n"].concat());
+		} else {
+			assert_eq!(res, expected);
+		}
+	}
+
+	// a self-closed root and compact mode
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::pretty().with_final_newline(true));
+	xml.empty_elem("root")?;
+	assert_eq!(String::try_from(xml).unwrap(), "<root/>\n");
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_final_newline(true));
+	xml.empty_elem("root")?;
+	assert_eq!(String::try_from(xml).unwrap(), "<root/>");
+	Ok(())
+}
+
+// ---- mode switching ------------------------------------------------------------
+
+#[test]
+fn mode_switch_compact_to_pretty() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(!xml.is_pretty());
+	xml.set_pretty_mode();
+	assert!(xml.is_pretty());
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <child/>\n</root>");
+	Ok(())
+}
+
+#[test]
+fn mode_switch_pretty_to_compact() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	assert!(xml.is_pretty());
+	xml.set_compact_mode();
+	assert!(!xml.is_pretty());
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><child/></root>");
+	Ok(())
+}
+
+#[test]
+fn compact_spacing_between_siblings() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_compact_spacing(true);
+	xml.begin_elem("a")?;
+	xml.empty_elem("b")?;
+	xml.begin_elem("c")?;
+	xml.text("text")?;
+	xml.end_elem()?;
+	xml.comment("note")?;
+	xml.begin_elem("d")?;
+	xml.attr("x", "1")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, r#"<a> <b/> <c>text</c> <!-- note --> <d x="1"/> </a>"#);
+	Ok(())
+}
+
+// ---- namespace getter ----------------------------------------------------------
+
+#[test]
+fn namespace_getter() {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(xml.namespace().is_none());
+	xml.set_namespace("ns");
+	assert_eq!(xml.namespace(), Some("ns"));
+	xml.unset_namespace();
+	assert!(xml.namespace().is_none());
+}
+
+// ---- processing instructions ---------------------------------------------------
+
+#[test]
+fn pi_in_prolog_and_content() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.pi("config", "")?;
+	xml.begin_elem("root")?;
+	xml.pi("reload", "")?;
+	xml.pi("target", "some data")?;
+	xml.empty_elem("child")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<?config?>\n<root>\n  <?reload?>\n  <?target some data?>\n  <child/>\n</root>"
+	);
+	Ok(())
+}
+
+#[test]
+fn stylesheet_pi_in_prolog() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.dtd("UTF-8")?;
+	xml.stylesheet_pi("text/xsl", "style.xsl?a=1&b=\"2\"")?;
+	assert_eq!(
+		xml.stylesheet_pi("text/css", "a?>b"),
+		Err(Error::InvalidProcessingInstruction)
+	);
+	xml.empty_elem("root")?;
+	assert_eq!(xml.stylesheet_pi("text/css", "style.css"), Err(Error::ContentAlreadyWritten));
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n",
+			"<?xml-stylesheet type=\"text/xsl\" href=\"style.xsl?a=1&amp;b=&quot;2&quot;\"?>\n",
+			"<root/>"
+		)
+	);
+	Ok(())
+}
+
+#[test]
+fn pi_with_pseudo_attributes() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.pi_attrs("render", [("mode", "fast"), ("note", "a < b & \"c\" 'd'")])?;
+	xml.begin_elem("root")?;
+	assert_eq!(xml.pi_attrs("xml", [("a", "b")]), Err(Error::InvalidName));
+	assert_eq!(xml.pi_attrs("render", [("a b", "c")]), Err(Error::InvalidName));
+	assert_eq!(
+		xml.pi_attrs("render", [("mode", "fast"), ("note", "?>")]),
+		Err(Error::InvalidProcessingInstruction)
+	);
+	xml.pi_attrs("reload", [])?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<?render mode=\"fast\" note=\"a &lt; b &amp; &quot;c&quot; 'd'\"?>\n<root>\n  <?reload?>\n</root>"
+	);
+	Ok(())
+}
+
+#[test]
+fn pi_as_first_child() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.begin_elem("section")?;
+	xml.attr("id", "1")?;
+	xml.pi("page-break", "before")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root>\n  <section id=\"1\">\n    <?page-break before?>\n  </section>\n</root>"
+	);
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.pi("target", "")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><?target?></root>");
+	Ok(())
+}
+
+#[test]
+fn pi_rejects_invalid_input() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	assert!(matches!(xml.pi("1target", ""), Err(Error::InvalidName)));
+	assert!(matches!(xml.pi("ns:target", ""), Err(Error::InvalidName)));
+	assert!(matches!(xml.pi("XmL", ""), Err(Error::InvalidName)));
+	assert!(matches!(xml.pi("target", "a ?> b"), Err(Error::InvalidProcessingInstruction)));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root/>");
+	Ok(())
+}
+
+// ---- default namespace ---------------------------------------------------------
+
+#[test]
+fn default_namespace_adds_no_prefix() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(!xml.default_namespace_active());
+	xml.begin_elem("root")?;
+	xml.ns_decl(&vec![
+		(None, "http://example.com/default"),
+		(Some("x"), "http://example.com/x"),
+	])?;
+	xml.set_default_namespace_active(true)?;
+	assert!(xml.default_namespace_active());
+	xml.empty_elem("plain")?;
+	xml.set_namespace("x");
+	xml.empty_elem("prefixed")?;
+	xml.unset_namespace();
+	xml.end_elem()?;
+	xml.set_default_namespace_active(false)?;
+	assert!(!xml.default_namespace_active());
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root xmlns=\"http://example.com/default\" xmlns:x=\"http://example.com/x\"><plain/><x:prefixed/></root>"
+	);
+	Ok(())
+}
+
+#[test]
+fn default_namespace_scoped_to_subtree() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_track_namespaces(true);
+	xml.begin_elem("root")?;
+	assert_eq!(
+		xml.set_default_namespace_active(true),
+		Err(Error::UndeclaredNamespace {
+			namespace: String::new()
+		})
+	);
+	xml.ns_decl(&vec![(None, "urn:default")])?;
+	assert!(xml.default_namespace_active());
+	xml.begin_elem("foreign")?;
+	xml.set_default_namespace_active(false)?;
+	assert!(!xml.default_namespace_active());
+	xml.empty_elem("none")?;
+	assert_eq!(
+		xml.set_default_namespace_active(false),
+		Ok(()),
+		"already inactive, nothing is written"
+	);
+	assert_eq!(
+		xml.set_default_namespace_active(true),
+		Err(Error::UndeclaredNamespace {
+			namespace: String::new()
+		}),
+		"declared empty by foreign"
+	);
+	xml.end_elem()?;
+	assert!(xml.default_namespace_active());
+	xml.empty_elem("within")?;
+	assert_eq!(
+		xml.set_default_namespace_active(false),
+		Err(Error::OpenNamespaceWithoutElement)
+	);
+	xml.end_elem()?;
+	assert!(!xml.default_namespace_active());
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root xmlns=\"urn:default\"><foreign xmlns=\"\"><none/></foreign><within/></root>"
+	);
+	Ok(())
+}
+
+// ---- element path --------------------------------------------------------------
+
+#[test]
+fn element_path_depth_three() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert_eq!(xml.element_path(), "");
+	xml.begin_elem("OTDS")?;
+	xml.set_namespace("st");
+	xml.begin_elem("node")?;
+	xml.unset_namespace();
+	xml.begin_elem("stuff")?;
+	assert_eq!(xml.element_path(), "OTDS/st:node/stuff");
+
+	let mut sink = bytes::BytesMut::new();
+	xml.write_path(&mut sink)?;
+	assert_eq!(&sink[..], b"OTDS/st:node/stuff");
+	Ok(())
+}
+
+#[test]
+fn element_and_namespace_stack() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(xml.element_stack().is_empty());
+	assert!(xml.namespace_stack().is_empty());
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.set_namespace("st");
+	xml.begin_elem("node")?;
+	xml.end_elem()?;
+	assert_eq!(xml.element_stack(), &[("root", true), ("child", true)]);
+	assert_eq!(xml.namespace_stack(), &[None, None]);
+
+	xml.end_elem()?;
+	xml.begin_elem("node")?;
+	assert_eq!(xml.element_stack(), &[("root", true), ("node", false)]);
+	assert_eq!(xml.namespace_stack(), &[None, Some("st")]);
+	Ok(())
+}
+
+// ---- into_inner ----------------------------------------------------------------
+
+#[test]
+fn into_inner_returns_buffer() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.end_elem()?;
+	let buf = xml.into_inner();
+	assert_eq!(&buf, b"<root/>");
+	Ok(())
+}
+
+#[test]
+fn try_into_inner_rejects_open_elements() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.begin_elem("open")?;
+	assert_eq!(xml.try_into_inner(), Err(Error::UnclosedElements { count: 2 }));
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_buffer_size(64);
+	xml.begin_elem("root")?;
+	xml.close()?;
+	assert_eq!(xml.try_into_inner()?, b"<root/>");
+	Ok(())
+}
+
+#[test]
+fn into_bytes_freezes_buffer() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(bytes::BytesMut::new());
+	xml.set_buffer_size(64);
+	xml.begin_elem("root")?;
+	xml.text("a & b")?;
+	xml.end_elem()?;
+	let frozen = xml.into_bytes();
+	let cloned = frozen.clone();
+	assert_eq!(&frozen[..], b"<root>a &amp; b</root>");
+	assert_eq!(frozen, cloned);
+	Ok(())
+}
+
+#[test]
+fn take_buffer_keeps_state() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_buffer_size(1024);
+	xml.begin_elem("log")?;
+	xml.set_namespace("x");
+	xml.empty_elem("entry")?;
+	let mut res = xml.take_buffer();
+	assert_eq!(&res, b"<log>\n  <x:entry/>");
+	xml.empty_elem("entry")?;
+	xml.unset_namespace();
+	xml.end_elem()?;
+	res.extend(xml.take_buffer());
+	assert!(xml.take_buffer().is_empty());
+
+	let res = String::from_utf8(res).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<log>\n  <x:entry/>\n  <x:entry/>\n</log>");
+	Ok(())
+}
+
+// ---- finish ----------------------------------------------------------------------
+
+#[test]
+fn finish_document_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_document_mode(true));
+	xml.dtd("UTF-8")?;
+	xml.comment("no root")?;
+	assert!(xml.is_document_empty());
+	assert_eq!(xml.finish(), Err(Error::EmptyDocument));
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_document_mode(true);
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	assert!(!xml.is_document_empty());
+	xml.finish()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><child/></root>");
+	Ok(())
+}
+
+#[test]
+fn finish_document_single_root() -> Result<(), Error> {
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_document_mode(true));
+	xml.empty_elem("first")?;
+	xml.empty_elem("second")?;
+	assert_eq!(xml.finish(), Err(Error::MultipleRoots));
+
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_document_mode(true));
+	xml.begin_elem("root")?;
+	assert_eq!(xml.try_into_inner(), Err(Error::UnclosedElements { count: 1 }));
+
+	let xml = XmlWriter::with_config(Vec::new(), Config::compact().with_document_mode(true));
+	assert_eq!(xml.try_into_inner(), Err(Error::EmptyDocument));
+
+	let mut xml = XmlWriter::with_config(Vec::new(), Config::compact().with_document_mode(true));
+	xml.begin_elem("root")?;
+	xml.empty_elem("child")?;
+	xml.end_elem()?;
+	xml.comment("trailer")?;
+	let res = String::from_utf8(xml.try_into_inner()?).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><child/></root><!-- trailer -->");
+	Ok(())
+}
+
+#[test]
+fn finish_fragment_mode() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert!(xml.is_document_empty());
+	xml.finish()?;
+	assert!(xml.into_inner().is_empty());
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let checkpoint = xml.checkpoint();
+	xml.empty_elem("a")?;
+	assert!(!xml.is_document_empty());
+	xml.rollback(checkpoint)?;
+	assert!(xml.is_document_empty());
+	Ok(())
+}
+
+#[test]
+fn finish_validates_output() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_validate_on_finish(true);
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.text("text")?;
+	// a stray raw write closing the wrong element
+	xml.write("</root>")?;
+	assert_eq!(
+		xml.finish(),
+		Err(Error::MalformedOutput {
+			reason: "mismatched end tag"
+		})
+	);
+
+	let config = Config::pretty()
+		.with_document_mode(true)
+		.with_validate_on_finish(true);
+	let mut xml = XmlWriter::with_config(Vec::new(), config.clone());
+	xml.empty_elem("root")?;
+	xml.write("trailing")?;
+	assert_eq!(
+		xml.finish(),
+		Err(Error::MalformedOutput {
+			reason: "content outside of the root element"
+		})
+	);
+
+	let mut xml = XmlWriter::with_config(Vec::new(), config);
+	xml.dtd("UTF-8")?;
+	xml.comment("a > b")?;
+	xml.begin_elem("root")?;
+	xml.attr("a", "1 > 0")?;
+	xml.cdata("</root>")?;
+	xml.empty_elem("child")?;
+	xml.finish()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	Ok(())
+}
+
+// ---- debug impl ----------------------------------------------------------------
+
+#[test]
+fn debug_format() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let s = format!("{xml:?}");
+	assert!(s.contains("XmlWriter"));
+	assert!(s.contains("root"));
+	assert_eq!(
+		&s,
+		"XmlWriter { stack: [(\"root\", false)], opened: true, pretty: false, newline: false, len: Some(5), pending: 0 }"
+	);
+	Ok(())
+}
+
+// ---- debug_dump ------------------------------------------------------------------
+
+#[test]
+fn debug_dump_attribute_per_line() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_buffer_size(16);
+	xml.dtd("UTF-8")?;
+	xml.begin_elem("root")?;
+	xml.attr_esc("id", "a > b")?;
+	xml.attr("kind", "x y")?;
+	xml.comment("note")?;
+	xml.begin_elem("item")?;
+	xml.attr("n", "1")?;
+	xml.text("text")?;
+	xml.end_elem()?;
+	xml.begin_elem("empty")?;
+	xml.attr("a", "1")?;
+	xml.attr("b", "")?;
+	xml.end_elem()?;
+
+	assert_eq!(
+		xml.debug_dump(),
+		concat!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n",
+			"<root\n",
+			"    id=\"a &gt; b\"\n",
+			"    kind=\"x y\">\n",
+			"  <!-- note -->\n",
+			"  <item\n",
+			"      n=\"1\">\n",
+			"    text\n",
+			"  </item>\n",
+			"  <empty\n",
+			"      a=\"1\"\n",
+			"      b=\"\"/>"
+		)
+	);
+	Ok(())
+}
+
+// ---- reformat ------------------------------------------------------------------
+
+#[test]
+fn reformat_round_trip() -> Result<(), Error> {
+	let compact = concat!(
+		"<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n",
+		"<log level='a \"b\"'><!-- start --><entry n=\"1\">a &amp; b &#160;</entry>",
+		"<data><![CDATA[<raw>]]></data><empty/></log>"
+	);
+	let pretty = woxml::reformat(compact, true)?;
+	assert_well_formed(&pretty);
+	assert_eq!(
+		&pretty,
+		concat!(
+			"<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n",
+			"<log level=\"a &quot;b&quot;\">\n",
+			"  <!-- start -->\n",
+			"  <entry n=\"1\">a &amp; b &#160;</entry>\n",
+			"  <data>\n",
+			"    <![CDATA[<raw>]]>\n",
+			"  </data>\n",
+			"  <empty/>\n",
+			"</log>"
+		)
+	);
+	let round_trip = woxml::reformat(&pretty, false)?;
+	assert_eq!(round_trip, compact.replace("'a \"b\"'", "\"a &quot;b&quot;\""));
+	assert_eq!(
+		woxml::reformat("<log><entry></log>", true),
+		Err(Error::MalformedInput {
+			reason: "mismatched end tag"
+		})
+	);
+	Ok(())
+}
+
+#[test]
+fn reformat_is_idempotent() -> Result<(), Error> {
+	let input = "<log><!-- x & y --><entry>a &lt; b</entry><p>Hello <b>world</b> <i>again</i>!</p></log>";
+	for pretty in [true, false] {
+		let once = woxml::reformat(input, pretty)?;
+		assert_well_formed(&once);
+		assert_eq!(woxml::reformat(&once, pretty)?, once);
+	}
+	assert_eq!(
+		woxml::reformat(input, true)?,
+		concat!(
+			"<log>\n",
+			"  <!-- x & y -->\n",
+			"  <entry>a &lt; b</entry>\n",
+			"  <p>Hello <b>world</b> <i>again</i>!</p>\n",
+			"</log>"
+		)
+	);
+	Ok(())
+}
+
+#[test]
+fn reformat_keeps_mixed_content() -> Result<(), Error> {
+	let input = "<a>Hello <b>world</b>!</a>";
+	assert_eq!(woxml::reformat(input, true)?, input);
+	assert_eq!(woxml::reformat(input, false)?, input);
+	assert_eq!(
+		woxml::reformat("<doc><p>Hi <em>you</em></p><list><item/></list></doc>", true)?,
+		"<doc>\n  <p>Hi <em>you</em></p>\n  <list>\n    <item/>\n  </list>\n</doc>"
+	);
+	Ok(())
+}
+
+// ---- close on empty stack ------------------------------------------------------
+
+#[test]
+fn close_on_empty_stack() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	// calling close with nothing open is a no-op
+	xml.close()?;
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "");
+	Ok(())
+}
+
+// ---- close_to ------------------------------------------------------------------
+
+#[test]
+fn close_to_exclusive() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("a")?;
+	xml.begin_elem("b")?;
+	xml.begin_elem("c")?;
+	xml.close_to("a", false)?;
+	xml.begin_elem("d")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<a><b><c/></b><d/></a>");
+	Ok(())
+}
+
+#[test]
+fn close_to_inclusive() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("a")?;
+	xml.begin_elem("b")?;
+	xml.begin_elem("c")?;
+	xml.close_to("b", true)?;
+	xml.begin_elem("d")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<a><b><c/></b><d/></a>");
+	Ok(())
+}
+
+#[test]
+fn error_close_to_unknown() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("a")?;
+	xml.begin_elem("b")?;
+	let err = xml.close_to("x", true).unwrap_err();
+	assert!(matches!(err, Error::CloseElement));
+	// nothing has been closed
+	xml.close()?;
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<a><b/></a>");
+	Ok(())
+}
+
+// ---- next_sibling ----------------------------------------------------------------
+
+#[test]
+fn next_sibling_records() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	assert_eq!(xml.next_sibling("record"), Err(Error::CloseElement));
+	xml.begin_elem("log")?;
+	xml.begin_elem("record")?;
+	xml.attr("id", "1")?;
+	xml.next_sibling("record")?;
+	xml.attr("id", "2")?;
+	xml.text("second")?;
+	xml.next_sibling("record")?;
+	xml.attr("id", "3")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<log>\n",
+			"  <record id=\"1\"/>\n",
+			"  <record id=\"2\">second</record>\n",
+			"  <record id=\"3\"/>\n",
+			"</log>"
+		)
+	);
+	Ok(())
+}
+
+// ---- open / end_elem_checked ----------------------------------------------------
+
+#[test]
+fn open_and_end_elem_checked() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let root = xml.open("root")?;
+	assert_eq!(root.name(), "root");
+	assert_eq!(root.depth(), 1);
+	let child = xml.open("child")?;
+	assert_eq!(child.depth(), 2);
+	xml.end_elem_checked(child)?;
+	xml.end_elem_checked(root)?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><child/></root>");
+	Ok(())
+}
+
+#[test]
+fn end_elem_checked_stale_token() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let root = xml.open("root")?;
+	let first = xml.open("item")?;
+	xml.end_elem()?;
+	let _second = xml.open("other")?;
+	// the token of the already closed elem is stale
+	assert!(matches!(xml.end_elem_checked(first), Err(Error::MismatchedClose)));
+	// the root is not the current elem
+	assert!(matches!(xml.end_elem_checked(root), Err(Error::MismatchedClose)));
+	Ok(())
+}
+
+// ---- checkpoint / rollback ------------------------------------------------------
+
+#[test]
+fn rollback_discards_speculative_content() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.attr("id", "1")?;
+	let checkpoint = xml.checkpoint();
+	xml.set_namespace("ns");
+	xml.begin_elem("optional")?;
+	xml.begin_elem("empty")?;
+	xml.rollback(checkpoint)?;
+	assert!(xml.namespace().is_none());
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root id=\"1\"/>");
+	Ok(())
+}
+
+#[test]
+fn rollback_keeps_previous_content() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(bytes::BytesMut::new());
+	xml.begin_elem("root")?;
+	xml.begin_elem("kept")?;
+	xml.end_elem()?;
+	let checkpoint = xml.checkpoint();
+	xml.text("dropped")?;
+	xml.rollback(checkpoint.clone())?;
+	xml.text("kept")?;
+	// a checkpoint from the future is rejected
+	let future = xml.checkpoint();
+	xml.rollback(checkpoint)?;
+	assert!(matches!(xml.rollback(future), Err(Error::InvalidCheckpoint)));
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><kept/></root>");
+	Ok(())
+}
+
+#[test]
+fn rollback_keeps_stacks_in_sync() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.set_namespace("a");
+	xml.begin_elem("outer")?;
+	let checkpoint = xml.checkpoint();
+	xml.set_namespace("b");
+	xml.begin_elem("inner")?;
+	xml.begin_elem("innermost")?;
+	xml.rollback(checkpoint)?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	// both stacks are empty, no desync is reported
+	assert_eq!(xml.end_elem(), Err(Error::CloseElement));
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><a:outer/></root>");
+	Ok(())
+}
+
+// ---- pop_last_element ------------------------------------------------------------
+
+#[test]
+fn pop_last_element_removes_closed_elem() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.empty_elem("kept")?;
+	xml.begin_elem("dropped")?;
+	xml.attr("a", "1")?;
+	xml.empty_elem("child")?;
+	xml.end_elem()?;
+	xml.pop_last_element()?;
+	assert_eq!(xml.pop_last_element(), Err(Error::NoLastElement));
+	xml.empty_elem("empty")?;
+	xml.pop_last_element()?;
+	xml.text("text")?;
+	assert_eq!(xml.pop_last_element(), Err(Error::NoLastElement));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <kept/>text</root>");
+	Ok(())
+}
+
+#[test]
+fn pop_last_element_restores_empty_document() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_record_events(true);
+	xml.set_document_mode(true);
+	xml.begin_elem("root")?;
+	xml.end_elem()?;
+	xml.pop_last_element()?;
+	assert!(xml.is_document_empty());
+	assert!(xml.take_events().is_empty());
+	assert_eq!(xml.finish(), Err(Error::EmptyDocument));
+	Ok(())
+}
+
+// ---- elem_if -------------------------------------------------------------------
+
+#[test]
+fn elem_if_false_writes_nothing() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.elem_if("optional", |xml| {
+		xml.attr("a", "1")?;
+		xml.begin_elem("child")?;
+		Ok(false)
+	})?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "");
+	Ok(())
+}
+
+#[test]
+fn elem_if_true_writes_elem() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.elem_if("empty", |_| Ok(false))?;
+	xml.elem_if("optional", |xml| {
+		xml.begin_elem("child")?;
+		xml.text("content")?;
+		Ok(true)
+	})?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><optional><child>content</child></optional></root>");
+	Ok(())
+}
+
+#[test]
+fn elem_if_false_keeps_parent_self_closing() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.elem_if("empty", |_| Ok(false))?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root/>");
+	Ok(())
+}
+
+#[test]
+fn elem_if_error_writes_nothing() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let result = xml.elem_if("optional", |xml| {
+		xml.attr("a", "1")?;
+		xml.begin_elem("child")?;
+		xml.text("content")?;
+		Err(Error::InvalidName)
+	});
+	assert_eq!(result, Err(Error::InvalidName));
+	xml.begin_elem("next")?;
+	xml.text("x")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <next>x</next>\n</root>");
+	Ok(())
+}
+
+// ---- each ----------------------------------------------------------------------
+
+#[test]
+fn each_writes_heterogeneous_items() -> Result<(), Error> {
+	enum Item {
+		Note(&'static str),
+		Link(&'static str),
+		Empty,
+	}
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("list")?;
+	xml.each([Item::Note("a & b"), Item::Link("#x"), Item::Empty], |xml, item| match item {
+		Item::Note(text) => {
+			xml.begin_elem("note")?;
+			xml.text(text)?;
+			xml.end_elem()
+		}
+		Item::Link(href) => {
+			xml.begin_elem("a")?;
+			xml.attr("href", href)?;
+			xml.empty_elem("img")?;
+			xml.end_elem()
+		}
+		Item::Empty => xml.comment("empty"),
+	})?;
+	// writing stops at the first error
+	let mut count = 0;
+	assert_eq!(
+		xml.each(["ok", "bad name", "skipped"], |xml, name| {
+			count += 1;
+			xml.set_validate_names(true);
+			xml.empty_elem(name)
+		}),
+		Err(Error::InvalidName)
+	);
+	assert_eq!(count, 2);
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<list><note>a &amp; b</note><a href=\"#x\"><img/></a><!-- empty --><ok/></list>"
+	);
+	Ok(())
+}
+
+// ---- event recording -----------------------------------------------------------
+
+#[test]
+fn record_events() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("ignored")?;
+	xml.set_record_events(true);
+	xml.attr_esc("id", "<1>")?;
+	xml.set_namespace("ns");
+	xml.begin_elem("child")?;
+	xml.text("a & b")?;
+	xml.end_elem()?;
+	xml.unset_namespace();
+	xml.empty_elem("leaf")?;
+	xml.comment("note")?;
+	xml.cdata("data")?;
+	xml.end_elem()?;
+
+	let events = xml.take_events();
+	assert_eq!(
+		events,
+		vec![
+			Event::Attr {
+				name: "id".into(),
+				value: "<1>".into()
+			},
+			Event::StartElem {
+				namespace: Some("ns"),
+				name: "child".into()
+			},
+			Event::Text("a & b".into()),
+			Event::EndElem {
+				namespace: Some("ns"),
+				name: "child".into()
+			},
+			Event::StartElem {
+				namespace: None,
+				name: "leaf".into()
+			},
+			Event::EndElem {
+				namespace: None,
+				name: "leaf".into()
+			},
+			Event::Comment("note".into()),
+			Event::Cdata("data".into()),
+			Event::EndElem {
+				namespace: None,
+				name: "ignored".into()
+			},
+		]
+	);
+	assert!(xml.take_events().is_empty());
+	Ok(())
+}
+
+#[test]
+fn record_events_disabled() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.set_record_events(true);
+	xml.text("text")?;
+	xml.set_record_events(false);
+	xml.end_elem()?;
+	assert!(xml.take_events().is_empty());
+	Ok(())
+}
+
+#[test]
+fn record_events_rollback() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_record_events(true);
+	xml.begin_elem("root")?;
+	let checkpoint = xml.checkpoint();
+	xml.elem_text("dropped", "text")?;
+	xml.rollback(checkpoint)?;
+	xml.end_elem()?;
+
+	assert_eq!(xml.take_events().len(), 2);
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn event_sink_collects_events() -> Result<(), Error> {
+	let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+	let mut xml = XmlWriter::event_mode(events.clone());
+	xml.begin_elem("root")?;
+	xml.attr("id", "1")?;
+	xml.elem_text("child", "a < b")?;
+	xml.end_elem()?;
+	xml.finish()?;
+
+	assert_eq!(
+		*events.lock().unwrap(),
+		vec![
+			Event::StartElem {
+				namespace: None,
+				name: "root".into()
+			},
+			Event::Attr {
+				name: "id".into(),
+				value: "1".into()
+			},
+			Event::StartElem {
+				namespace: None,
+				name: "child".into()
+			},
+			Event::Text("a < b".into()),
+			Event::EndElem {
+				namespace: None,
+				name: "child".into()
+			},
+			Event::EndElem {
+				namespace: None,
+				name: "root".into()
+			},
+		]
+	);
+	Ok(())
+}
+
+#[test]
+fn event_sink_error_aborts() -> Result<(), Error> {
+	struct Rejecting;
+	impl EventSink<'_> for Rejecting {
+		fn event(&mut self, event: Event<'_>) -> Result<(), Error> {
+			match event {
+				Event::Comment(_) => Err(Error::InvalidComment),
+				_ => Ok(()),
+			}
+		}
+	}
+
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_event_sink(Rejecting);
+	xml.begin_elem("root")?;
+	assert_eq!(xml.comment("rejected"), Err(Error::InvalidComment));
+	xml.unset_event_sink();
+	xml.comment("kept")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root><!-- kept --></root>");
+	Ok(())
+}
+
+#[test]
+fn event_sink_needs_not_be_sync() -> Result<(), Error> {
+	#[derive(Default)]
+	struct Counting {
+		count: core::cell::Cell<usize>,
+	}
+	impl EventSink<'_> for Counting {
+		fn event(&mut self, _event: Event<'_>) -> Result<(), Error> {
+			self.count.set(self.count.get() + 1);
+			Ok(())
+		}
+	}
+
+	let mut counting = Counting::default();
+	let mut xml = XmlWriter::event_mode(&mut counting);
+	xml.begin_elem("root")?;
+	xml.empty_elem("child")?;
+	xml.end_elem()?;
+	drop(xml);
+	assert_eq!(counting.count.get(), 4);
+	Ok(())
+}
+
+/// The events sent to a sink by an [`XmlWriter`] in event mode while `f` writes.
+#[cfg(feature = "std")]
+fn sink_events<F>(f: F) -> Result<Vec<Event<'static>>, Error>
+where
+	F: FnOnce(&mut XmlWriter<'static, woxml::Discard>) -> Result<(), Error>,
+{
+	let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+	let mut xml = XmlWriter::event_mode(events.clone());
+	f(&mut xml)?;
+	drop(xml);
+	Ok(events.lock().unwrap().clone())
+}
+
+#[cfg(feature = "std")]
+fn start(name: &'static str) -> Event<'static> {
+	Event::StartElem {
+		namespace: None,
+		name: name.into(),
+	}
+}
+
+#[cfg(feature = "std")]
+fn end(name: &'static str) -> Event<'static> {
+	Event::EndElem {
+		namespace: None,
+		name: name.into(),
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn event_sink_attr_streamed() -> Result<(), Error> {
+	let events = sink_events(|xml| {
+		xml.begin_elem("root")?;
+		xml.attr_streamed("data", chunks("a & b", 2))?;
+		xml.end_elem()
+	})?;
+	assert_eq!(
+		events,
+		vec![
+			start("root"),
+			Event::Attr {
+				name: "data".into(),
+				value: "a & b".into()
+			},
+			end("root"),
+		]
+	);
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn event_sink_encoded_text() -> Result<(), Error> {
+	let events = sink_events(|xml| {
+		xml.elem_base64("b", b"hi")?;
+		xml.elem_hex("h", &[0xab])
+	})?;
+	assert_eq!(
+		events,
+		vec![
+			start("b"),
+			Event::Text("aGk=".into()),
+			end("b"),
+			start("h"),
+			Event::Text("ab".into()),
+			end("h"),
+		]
+	);
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn event_sink_references() -> Result<(), Error> {
+	let events = sink_events(|xml| {
+		xml.begin_elem("p")?;
+		xml.raw_entity("nbsp")?;
+		xml.char_ref(65)?;
+		xml.char_ref_hex(0x42)?;
+		xml.end_elem()
+	})?;
+	assert_eq!(
+		events,
+		vec![
+			start("p"),
+			Event::EntityRef("nbsp".into()),
+			Event::Text("A".into()),
+			Event::Text("B".into()),
+			end("p"),
+		]
+	);
+	Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn event_sink_processing_instructions() -> Result<(), Error> {
+	let events = sink_events(|xml| {
+		xml.pi("target", "data")?;
+		xml.pi_attrs("xml-stylesheet", [("type", "text/xsl"), ("href", "a&b.xsl")])
+	})?;
+	assert_eq!(
+		events,
+		vec![
+			Event::Pi {
+				target: "target".into(),
+				data: "data".into()
+			},
+			Event::Pi {
+				target: "xml-stylesheet".into(),
+				data: "type=\"text/xsl\" href=\"a&b.xsl\"".into()
+			},
+		]
+	);
+	Ok(())
+}
+
+// ---- error cases ---------------------------------------------------------------
+
+#[test]
+fn error_attr_without_element() {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let err = xml.attr("key", "val").unwrap_err();
+	assert!(matches!(err, Error::WriteWithoutElement));
+}
+
+#[test]
+fn error_attr_esc_without_element() {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let err = xml.attr_esc("key", "val").unwrap_err();
+	assert!(matches!(err, Error::WriteWithoutElement));
+}
+
+#[test]
+fn error_ns_decl_without_element() {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let nsmap = vec![(None, "http://example.com/")];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::OpenNamespaceWithoutElement));
+}
+
+#[test]
+fn error_end_elem_without_open() {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	let err = xml.end_elem().unwrap_err();
+	assert!(matches!(err, Error::CloseElement));
+}
+
+#[test]
+fn error_equality() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	assert_eq!(xml.end_elem(), Err(Error::CloseElement));
+	assert_eq!(xml.attr("key", "val"), Err(Error::WriteWithoutElement));
+	xml.begin_elem("root")?;
+	assert_eq!(
+		xml.ns_decl(&vec![(Some("a"), "urn:a"), (Some("a"), "urn:b")]),
+		Err(Error::DuplicateNamespacePrefix { prefix: "a".into() })
+	);
+	assert_ne!(Error::CloseElement, Error::MismatchedClose);
+	Ok(())
+}
+
+#[test]
+fn error_from_other_errors() {
+	fn format() -> Result<(), Error> {
+		Err(core::fmt::Error)?
+	}
+	assert_eq!(format(), Err(Error::Fmt));
+	let infallible: Result<u8, core::convert::Infallible> = Ok(1);
+	assert_eq!(infallible.map_err(Error::from), Ok(1));
+	#[cfg(feature = "std")]
+	{
+		fn io() -> Result<(), Error> {
+			Err(std::io::Error::from(std::io::ErrorKind::BrokenPipe))?
+		}
+		assert_eq!(io(), Err(Error::Io(std::io::ErrorKind::BrokenPipe)));
+		assert_eq!(format!("{}", io().unwrap_err()), "I/O error: broken pipe");
+	}
+}
+
+// ---- ns_decl -------------------------------------------------------------------
+
+#[test]
+fn ns_decl_escapes_uri() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let nsmap = vec![
+		(None, "urn:x?a=1&b=<2>"),
+		(Some("q"), "http://example.com/?\"q\""),
+	];
+	xml.ns_decl(&nsmap)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root xmlns=\"urn:x?a=1&amp;b=&lt;2&gt;\" xmlns:q=\"http://example.com/?&quot;q&quot;\"/>"
+	);
+	Ok(())
+}
+
+#[test]
+fn error_ns_decl_invalid_prefix() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let nsmap = vec![
+		(Some("ok"), "urn:ok"),
+		(Some("not:ok"), "urn:not-ok"),
+	];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::InvalidName));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root/>");
+	Ok(())
+}
+
+#[test]
+fn error_ns_decl_duplicate_prefix() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let nsmap = vec![
+		(Some("st"), "urn:a"),
+		(None, "urn:default"),
+		(Some("st"), "urn:b"),
+	];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::DuplicateNamespacePrefix { prefix } if prefix == "st"));
+	let nsmap = vec![(None, "urn:a"), (None, "urn:b")];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::DuplicateNamespacePrefix { prefix } if prefix.is_empty()));
+	let nsmap = vec![
+		(Some("st"), "urn:a"),
+		(Some("sx"), "urn:b"),
+		(None, "urn:c"),
+	];
+	xml.ns_decl(&nsmap)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root xmlns:st=\"urn:a\" xmlns:sx=\"urn:b\" xmlns=\"urn:c\"/>");
+	Ok(())
+}
+
+#[test]
+fn ns_decl_iter_array() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.ns_decl_iter([(None, "urn:default"), (Some("st"), "urn:st")])?;
+	let err = xml
+		.ns_decl_iter([(Some("a"), "urn:a"), (Some("a"), "urn:b")])
+		.unwrap_err();
+	assert!(matches!(err, Error::DuplicateNamespacePrefix { prefix } if prefix == "a"));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root xmlns=\"urn:default\" xmlns:st=\"urn:st\"/>");
+	Ok(())
+}
+
+#[test]
+fn error_ns_decl_reserved_prefix() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	let nsmap = vec![(Some("a"), "urn:a"), (Some("xmlns"), "urn:x")];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::ReservedPrefix { prefix } if prefix == "xmlns"));
+	let nsmap = vec![(Some("xml"), "urn:x")];
+	let err = xml.ns_decl(&nsmap).unwrap_err();
+	assert!(matches!(err, Error::ReservedPrefix { prefix } if prefix == "xml"));
+	let nsmap = vec![(Some("xml"), "http://www.w3.org/XML/1998/namespace")];
+	xml.ns_decl(&nsmap)?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root xmlns:xml=\"http://www.w3.org/XML/1998/namespace\"/>");
+	Ok(())
+}
+
+// ---- empty_elem_ns_attrs ------------------------------------------------------------
+
+#[test]
+fn empty_elem_ns_attrs() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_validate_names(true);
+	xml.set_namespace("svg");
+	xml.begin_elem("svg")?;
+	xml.ns_decl(&vec![
+		(Some("svg"), "http://www.w3.org/2000/svg"),
+		(Some("xlink"), "http://www.w3.org/1999/xlink"),
+	])?;
+	xml.empty_elem_ns_attrs(Some("svg"), "use", &[("xlink:href", "#x")])?;
+	xml.empty_elem_ns_attrs(None, "plain", &[("a", "<1>"), ("b", "2")])?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			r#"<svg:svg xmlns:svg="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">"#,
+			r##"<svg:use xlink:href="#x"/><plain a="&lt;1&gt;" b="2"/></svg:svg>"##
+		)
+	);
+	Ok(())
+}
+
+#[test]
+fn validated_names() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	// without validation anything goes
+	xml.begin_elem("1root")?;
+	xml.set_validate_names(true);
+	assert!(matches!(
+		xml.empty_elem_ns_attrs(Some("a:b"), "use", &[]),
+		Err(Error::InvalidName)
+	));
+	assert!(matches!(
+		xml.empty_elem_ns_attrs(None, "use", &[("1a", "x")]),
+		Err(Error::InvalidName)
+	));
+	assert!(matches!(xml.begin_elem("a b"), Err(Error::InvalidName)));
+	assert!(matches!(xml.elem("<x>"), Err(Error::InvalidName)));
+	assert!(matches!(xml.empty_elem("-x"), Err(Error::InvalidName)));
+	assert!(matches!(xml.attr("a=", "x"), Err(Error::InvalidName)));
+	assert!(matches!(xml.attr_esc("", "x"), Err(Error::InvalidName)));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<1root/>");
+	Ok(())
+}
+
+#[test]
+fn reserved_names() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	// accepted by default
+	xml.begin_elem("xmlData")?;
+	xml.set_reject_reserved_names(true);
+	assert_eq!(xml.empty_elem("xmlFoo"), Err(Error::ReservedName { name: "xmlFoo".into() }));
+	assert_eq!(xml.attr("XMLdata", "x"), Err(Error::ReservedName { name: "XMLdata".into() }));
+	assert_eq!(
+		xml.attr_ns("p", "xml-id", "x"),
+		Err(Error::ReservedName { name: "xml-id".into() })
+	);
+	xml.attr("xml:lang", "en")?;
+	xml.attr_ns("xml", "space", "preserve")?;
+	xml.attr("xmlns:p", "urn:p")?;
+	xml.empty_elem("mxl")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		r#"<xmlData xml:lang="en" xml:space="preserve" xmlns:p="urn:p"><mxl/></xmlData>"#
+	);
+	Ok(())
+}
+
+// ---- begin_elem_ns_decl -------------------------------------------------------
+
+#[test]
+fn begin_elem_ns_decl_soap_envelope() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem_ns_decl("soap", "Envelope", "http://www.w3.org/2003/05/soap-envelope")?;
+	xml.begin_elem("Body")?;
+	xml.begin_elem_ns_decl("m", "GetPrice", "urn:m")?;
+	xml.empty_elem("Item")?;
+	xml.end_elem()?;
+	assert_eq!(xml.namespace(), Some("soap"));
+	xml.empty_elem("Trailer")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	assert_eq!(xml.namespace(), None);
+	assert!(matches!(
+		xml.begin_elem_ns_decl("xmlns", "x", "urn:x"),
+		Err(Error::ReservedPrefix { .. })
+	));
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\">\n",
+			"  <soap:Body>\n",
+			"    <m:GetPrice xmlns:m=\"urn:m\">\n",
+			"      <m:Item/>\n",
+			"    </m:GetPrice>\n",
+			"    <soap:Trailer/>\n",
+			"  </soap:Body>\n",
+			"</soap:Envelope>"
+		)
+	);
+	Ok(())
+}
+
+// ---- enter_ns_scope ------------------------------------------------------------
+
+#[test]
+fn enter_ns_scope_restores_namespace() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem_ns_decl("soap", "Envelope", "urn:soap")?;
+	xml.begin_elem("Body")?;
+	xml.enter_ns_scope(&[(Some("m"), "urn:m"), (Some("t"), "urn:t")], Some("m"))?;
+	assert_eq!(xml.namespace(), Some("m"));
+	xml.empty_elem("GetPrice")?;
+	xml.end_elem()?;
+	assert_eq!(xml.namespace(), Some("soap"));
+	xml.empty_elem("Trailer")?;
+	xml.end_elem()?;
+	assert_eq!(xml.namespace(), None);
+	assert_eq!(
+		xml.enter_ns_scope(&[(Some("m"), "urn:m")], Some("m")),
+		Err(Error::OpenNamespaceWithoutElement)
+	);
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		concat!(
+			"<soap:Envelope xmlns:soap=\"urn:soap\">",
+			"<soap:Body xmlns:m=\"urn:m\" xmlns:t=\"urn:t\"><m:GetPrice/></soap:Body>",
+			"<soap:Trailer/>",
+			"</soap:Envelope>"
+		)
+	);
+	Ok(())
+}
+
+// ---- nested namespaces ---------------------------------------------------------
+
+#[test]
+fn nested_namespace_change() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_namespace("a");
+	xml.begin_elem("outer")?;
+	xml.set_namespace("b");
+	xml.begin_elem("inner")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<a:outer><b:inner/></a:outer>");
+	Ok(())
+}
+
+// ---- empty_elem without namespace ----------------------------------------------
+
+#[test]
+fn empty_elem_no_namespace() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.empty_elem("leaf")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><leaf/></root>");
+	Ok(())
+}
+
+// ---- pretty indentation details ------------------------------------------------
+
+#[test]
+fn pretty_deep_nesting() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("a")?;
+	xml.begin_elem("b")?;
+	xml.begin_elem("c")?;
+	xml.text("leaf")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<a>\n  <b>\n    <c>leaf</c>\n  </b>\n</a>");
+	Ok(())
+}
+
+#[test]
+fn pretty_empty_elem_no_namespace() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.empty_elem("br")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <br/>\n</root>");
+	Ok(())
+}
+
+#[test]
+fn pretty_blank_line_between_top_level() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.set_blank_line_between_top_level(true);
+	xml.begin_elem("root")?;
+	xml.empty_elem("a")?;
+	xml.begin_elem("b")?;
+	xml.empty_elem("b1")?;
+	xml.empty_elem("b2")?;
+	xml.end_elem()?;
+	xml.begin_elem("c")?;
+	xml.text("text")?;
+	xml.end_elem()?;
+	xml.elem_text("d", "text")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(
+		&res,
+		"<root>\n  <a/>\n\n  <b>\n    <b1/>\n    <b2/>\n  </b>\n\n  <c>text</c>\n\n  <d>text</d>\n</root>"
+	);
+	Ok(())
+}
+
+#[test]
+fn compact_ignores_blank_line_between_top_level() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_blank_line_between_top_level(true);
+	xml.begin_elem("root")?;
+	xml.empty_elem("a")?;
+	xml.empty_elem("b")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<root><a/><b/></root>");
+	Ok(())
+}
+
+// ---- close() with open elements ------------------------------------------------
+
+#[test]
+fn close_with_open_elements() -> Result<(), Error> {
+	// close() must auto-close all open elements without explicit end_elem() calls
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root><child/></root>");
+	Ok(())
+}
+
+#[test]
+fn close_with_open_elements_pretty() -> Result<(), Error> {
+	let mut xml = XmlWriter::pretty_mode(Vec::new());
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	xml.begin_elem("leaf")?;
+	xml.close()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root>\n  <child>\n    <leaf/>\n  </child>\n</root>");
+	Ok(())
+}
+
+// ---- unchecked attributes ----------------------------------------------------
+
+#[test]
+fn attr_unchecked_produces_malformed_xml() -> Result<(), Error> {
+	// `attr` neither validates nor escapes, so it is up to the caller to
+	// only pass allowed characters. Here it produces malformed XML.
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.begin_elem("node")?;
+	xml.attr("'unescaped'", "\"123\"")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_eq!(&res, "<node 'unescaped'=\"\"123\"\"/>");
+	#[cfg(feature = "std")]
+	assert!(std::panic::catch_unwind(|| assert_well_formed(&res)).is_err());
+	Ok(())
+}
+
+// ---- arrayvec ------------------------------------------------------------------
+
+#[cfg(feature = "arrayvec")]
+#[test]
+fn arrayvec_buffer() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(arrayvec::ArrayVec::<u8, 32>::new());
+	xml.begin_elem("root")?;
+	xml.elem_text("a", "b")?;
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root/><a>b</a>");
+	Ok(())
+}
+
+#[cfg(feature = "arrayvec")]
+#[test]
+fn arrayvec_buffer_full() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(arrayvec::ArrayVec::<u8, 8>::new());
+	xml.begin_elem("root")?;
+	let err = xml.text("too long for the buffer").unwrap_err();
+	assert!(matches!(err, Error::BufferFull));
+	// the buffer has been filled up to its capacity
+	assert_eq!(&xml.into_inner()[..], b"<root>to");
+	Ok(())
+}
+
+// ---- tokio ---------------------------------------------------------------------
+
+/// A mock async sink accepting at most 4 bytes per write, optionally failing on flush.
+#[cfg(feature = "tokio")]
+#[derive(Default)]
+struct MockSink {
+	received: Vec<u8>,
+	flushes: usize,
+	fail_flush: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for MockSink {
+	fn poll_write(
+		mut self: core::pin::Pin<&mut Self>,
+		_cx: &mut core::task::Context<'_>,
+		buf: &[u8],
+	) -> core::task::Poll<std::io::Result<usize>> {
+		let n = buf.len().min(4);
+		self.received.extend_from_slice(&buf[..n]);
+		core::task::Poll::Ready(Ok(n))
+	}
+
+	fn poll_flush(
+		mut self: core::pin::Pin<&mut Self>,
+		_cx: &mut core::task::Context<'_>,
+	) -> core::task::Poll<std::io::Result<()>> {
+		if self.fail_flush {
+			return core::task::Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()));
+		}
+		self.flushes += 1;
+		core::task::Poll::Ready(Ok(()))
+	}
+
+	fn poll_shutdown(
+		self: core::pin::Pin<&mut Self>,
+		_cx: &mut core::task::Context<'_>,
+	) -> core::task::Poll<std::io::Result<()>> {
+		core::task::Poll::Ready(Ok(()))
+	}
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_buffered_writer_flush_to() -> Result<(), Error> {
+	let mut sink = MockSink::default();
+	let mut xml = XmlWriter::compact_mode(woxml::AsyncBufferedWriter::new());
+	xml.begin_elem("log")?;
+	xml.begin_elem("entry")?;
+	xml.text("first")?;
+	xml.end_elem()?;
+	assert_eq!(xml.take_buffer().flush_to(&mut sink).await?, 25);
+	xml.begin_elem("entry")?;
+	xml.text("second")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	xml.flush()?;
+
+	let mut buffer = xml.take_buffer();
+	assert_eq!(buffer.flush_to(&mut sink).await?, 27);
+	// the buffer has been drained
+	assert_eq!(buffer.flush_to(&mut sink).await?, 0);
+	assert_eq!(sink.flushes, 3);
+
+	let res = String::from_utf8(sink.received).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<log><entry>first</entry><entry>second</entry></log>");
+	Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_buffered_writer_sink_error() -> Result<(), Error> {
+	let mut sink = MockSink {
+		fail_flush: true,
+		..MockSink::default()
+	};
+	let mut xml = XmlWriter::compact_mode(woxml::AsyncBufferedWriter::with_capacity(64));
+	xml.empty_elem("root")?;
+	xml.flush()?;
+
+	let mut buffer = xml.take_buffer();
+	assert_eq!(
+		buffer.flush_to(&mut sink).await,
+		Err(Error::Io(std::io::ErrorKind::BrokenPipe))
+	);
+	// the output is kept for a retry
+	sink.fail_flush = false;
+	assert_eq!(buffer.flush_to(&mut sink).await?, 7);
+	assert_eq!(&sink.received, b"<root/><root/>");
+	Ok(())
+}
+
+// ---- max output bytes ------------------------------------------------------------
+
+#[test]
+fn max_output_bytes_truncates() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(Vec::new());
+	xml.set_max_output_bytes(18);
+	xml.begin_elem("log")?;
+	xml.attr("level", "info")?;
+	assert!(!xml.is_truncated());
+	xml.begin_elem("entry")?;
+	assert!(xml.is_truncated());
+	// further writes are no-ops
+	xml.text("discarded")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+
+	assert_eq!(&String::try_from(xml).unwrap(), "<log level=\"info\">");
+	Ok(())
+}
+
+#[test]
+fn max_output_bytes_closes_open_elements() -> Result<(), Error> {
+	let config = Config::pretty()
+		.with_max_output_bytes(64)
+		.with_truncation_headroom(16);
+	let mut xml = XmlWriter::with_config(Vec::new(), config);
+	xml.set_buffer_size(8);
+	xml.begin_elem("log")?;
+	for index in 0..10 {
+		xml.begin_elem("entry")?;
+		xml.attr("n", &format!("{index}"))?;
+		xml.text("text")?;
+		xml.end_elem()?;
+	}
+	assert!(xml.is_truncated());
+	xml.end_elem()?;
+	xml.finish()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert!(res.len() <= 64);
+	assert_eq!(&res, "<log>\n  <entry n=\"0\">text</entry>\n  </log>");
+
+	// the buffer has to support truncation
+	let mut storage = [0u8; 32];
+	let mut xml = XmlWriter::with_config(&mut storage[..], Config::compact().with_truncation_headroom(8));
+	xml.set_max_output_bytes(16);
+	assert_eq!(xml.elem_text("entry", "too long"), Err(Error::TruncateUnsupported));
+	Ok(())
+}
+
+// ---- bounded BytesMut ----------------------------------------------------------
+
+#[test]
+fn bytes_limit_buffer_full() -> Result<(), Error> {
+	use bytes::BufMut;
+
+	let mut xml = XmlWriter::compact_mode(bytes::BytesMut::new().limit(8));
+	xml.begin_elem("root")?;
+	assert_eq!(xml.remaining_capacity(), Some(3));
+	let checkpoint = xml.checkpoint();
+	assert_eq!(xml.text("too long for the buffer"), Err(Error::BufferFull));
+	// the buffer has been filled up to its limit
+	assert_eq!(xml.remaining_capacity(), Some(0));
+	xml.rollback(checkpoint)?;
+	assert_eq!(xml.remaining_capacity(), Some(3));
+	xml.end_elem()?;
+
+	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
+	assert_eq!(&res, "<root/>");
 	Ok(())
 }
 
@@ -403,7 +3747,6 @@ fn create_xml(
 	writer.begin_elem("node")?;
 	writer.attr_esc("name", "\"123\"")?;
 	writer.attr("id", "abc")?;
-	writer.attr("'unescaped'", "\"123\"")?;
 	writer.text("'text'")?;
 	writer.end_elem()?;
 	writer.unset_namespace();
@@ -428,10 +3771,11 @@ fn compact() -> Result<(), woxml::Error> {
 	create_xml(&mut writer, &nsmap)?;
 
 	let xml = String::try_from(writer).unwrap();
+	assert_well_formed(&xml);
 	println!("{}", &xml);
 	assert_eq!(
 		&xml,
-		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\"><!-- nice to see you --><st:success/><st:node name=\"&quot;123&quot;\" id=\"abc\" \'unescaped\'=\"\"123\"\">&apos;text&apos;</st:node><stuff><![CDATA[blablab]]></stuff><no_children/></OTDS>"
+		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\"><!-- nice to see you --><st:success/><st:node name=\"&quot;123&quot;\" id=\"abc\">&apos;text&apos;</st:node><stuff><![CDATA[blablab]]></stuff><no_children/></OTDS>"
 	);
 	Ok(())
 }
@@ -447,10 +3791,11 @@ fn pretty() -> Result<(), woxml::Error> {
 	create_xml(&mut writer, &nsmap)?;
 
 	let xml = String::try_from(writer).unwrap();
+	assert_well_formed(&xml);
 	println!("{}", &xml);
 	assert_eq!(
 		&xml,
-		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\">\n  <!-- nice to see you -->\n  <st:success/>\n  <st:node name=\"&quot;123&quot;\" id=\"abc\" \'unescaped\'=\"\"123\"\">&apos;text&apos;</st:node>\n  <stuff>\n    <![CDATA[blablab]]>\n  </stuff>\n  <no_children/>\n</OTDS>"
+		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\">\n  <!-- nice to see you -->\n  <st:success/>\n  <st:node name=\"&quot;123&quot;\" id=\"abc\">&apos;text&apos;</st:node>\n  <stuff>\n    <![CDATA[blablab]]>\n  </stuff>\n  <no_children/>\n</OTDS>"
 	);
 	Ok(())
 }
@@ -461,12 +3806,14 @@ fn comment() -> Result<(), woxml::Error> {
 	xml.comment("comment")?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<!-- comment -->");
 
 	let mut xml = XmlWriter::compact_mode(Vec::new());
 	xml.comment("comment")?;
 
 	let res = String::try_from(xml).unwrap();
+	assert_well_formed(&res);
 	assert_eq!(&res, "<!-- comment -->");
 	Ok(())
 }
@@ -487,7 +3834,6 @@ fn buffer() -> Result<(), woxml::Error> {
 	writer.begin_elem("node")?;
 	writer.attr_esc("name", "\"123\"")?;
 	writer.attr("id", "abc")?;
-	writer.attr("'unescaped'", "\"123\"")?;
 	writer.text("'text'")?;
 	writer.end_elem()?;
 	writer.unset_namespace();
@@ -499,10 +3845,11 @@ fn buffer() -> Result<(), woxml::Error> {
 	writer.flush()?;
 
 	let xml = String::try_from(writer).unwrap();
+	assert_well_formed(&xml);
 	println!("{}", &xml);
 	assert_eq!(
 		&xml,
-		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\"><!-- nice to see you --><st:success/><st:node name=\"&quot;123&quot;\" id=\"abc\" \'unescaped\'=\"\"123\"\">&apos;text&apos;</st:node><stuff><![CDATA[blablab]]></stuff></OTDS>"
+		"<OTDS xmlns=\"http://localhost/\" xmlns:st=\"http://127.0.0.1/\"><!-- nice to see you --><st:success/><st:node name=\"&quot;123&quot;\" id=\"abc\">&apos;text&apos;</st:node><stuff><![CDATA[blablab]]></stuff></OTDS>"
 	);
 	Ok(())
 }
@@ -3,6 +3,11 @@
 //! Tests for the [`Write`] traits default [`Write::write_all`] implementation.
 
 use std::vec::Vec;
+#[cfg(feature = "std")]
+use std::{
+	sync::{Arc, Mutex},
+	thread,
+};
 use woxml::{Error, Write, XmlWriter};
 
 /// A Write implementation that writes in fixed-size chunks and relies on the
@@ -100,3 +105,187 @@ fn default_write_all_empty_buf() -> Result<(), Error> {
 	w.write_all(b"")?;
 	Ok(())
 }
+
+#[test]
+fn default_truncate_unsupported() -> Result<(), Error> {
+	// ChunkWriter does not override len/truncate, so rollback is not possible
+	let mut xml = XmlWriter::compact_mode(ChunkWriter::new(3));
+	xml.begin_elem("root")?;
+	let checkpoint = xml.checkpoint();
+	xml.begin_elem("child")?;
+	assert!(xml.rollback(checkpoint).is_err());
+
+	let mut w = ChunkWriter::new(3);
+	assert!(w.len().is_none());
+	assert!(w.is_empty().is_none());
+	assert!(matches!(w.truncate(0), Err(Error::TruncateUnsupported)));
+	Ok(())
+}
+
+#[test]
+const fn writer_is_send() {
+	const fn assert_send<T: Send>() {}
+	assert_send::<XmlWriter<'static, Vec<u8>>>();
+	#[cfg(feature = "std")]
+	assert_send::<XmlWriter<'static, Arc<Mutex<Vec<u8>>>>>();
+}
+
+#[cfg(feature = "std")]
+#[test]
+#[allow(clippy::unwrap_used)]
+fn shared_sink_across_threads() -> Result<(), Error> {
+	let sink = Arc::new(Mutex::new(Vec::new()));
+	let handles: Vec<_> = ["a", "b"]
+		.into_iter()
+		.map(|name| {
+			let sink = Arc::clone(&sink);
+			thread::spawn(move || -> Result<(), Error> {
+				let mut xml = XmlWriter::compact_mode(sink);
+				for _ in 0..100 {
+					// a single write is never interleaved with writes of other writers
+					xml.write(&["<", name, "/>"].concat())?;
+				}
+				xml.flush()
+			})
+		})
+		.collect();
+	for handle in handles {
+		handle.join().unwrap()?;
+	}
+
+	let res = String::from_utf8(sink.lock().unwrap().clone()).unwrap();
+	assert_eq!(res.len(), 800);
+	assert_eq!(res.matches("<a/>").count(), 100);
+	assert_eq!(res.matches("<b/>").count(), 100);
+	Ok(())
+}
+
+/// A Write implementation counting the calls of `write`.
+#[derive(Default)]
+struct CountingWriter {
+	buf: Vec<u8>,
+	writes: usize,
+}
+
+impl Write for CountingWriter {
+	fn flush(&mut self) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+		self.writes += 1;
+		self.buf.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn len(&self) -> Option<usize> {
+		Some(self.buf.len())
+	}
+
+	fn truncate(&mut self, len: usize) -> Result<(), Error> {
+		self.buf.truncate(len);
+		Ok(())
+	}
+}
+
+fn create_xml(xml: &mut XmlWriter<'_, CountingWriter>) -> Result<(), Error> {
+	xml.begin_elem("root")?;
+	for _ in 0..10 {
+		xml.begin_elem("child")?;
+		xml.attr_esc("name", "\"value\"")?;
+		xml.text("some text")?;
+		xml.end_elem()?;
+	}
+	xml.end_elem()?;
+	xml.flush()
+}
+
+#[test]
+fn buffer_size_reduces_writes() -> Result<(), Error> {
+	let mut unbuffered = XmlWriter::pretty_mode(CountingWriter::default());
+	create_xml(&mut unbuffered)?;
+	let unbuffered = unbuffered.into_inner();
+
+	let mut buffered = XmlWriter::pretty_mode(CountingWriter::default());
+	buffered.set_buffer_size(64);
+	create_xml(&mut buffered)?;
+	let buffered = buffered.into_inner();
+
+	assert_eq!(buffered.buf, unbuffered.buf);
+	assert!(buffered.writes * 10 < unbuffered.writes);
+	Ok(())
+}
+
+#[test]
+fn buffer_size_pending_output() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(CountingWriter::default());
+	xml.set_buffer_size(1024);
+	xml.begin_elem("root")?;
+	xml.begin_elem("child")?;
+	// output is pending until it is handed over at once
+	assert_eq!(xml.into_inner().writes, 1);
+
+	// rollback of pending output and output already handed to the buffer
+	let mut xml = XmlWriter::compact_mode(CountingWriter::default());
+	xml.set_buffer_size(4);
+	xml.begin_elem("root")?;
+	let checkpoint_root = xml.checkpoint();
+	xml.begin_elem("child")?;
+	xml.text("text")?;
+	xml.rollback(checkpoint_root)?;
+	xml.end_elem()?;
+	xml.flush()?;
+	assert_eq!(xml.into_inner().buf, b"<root/>");
+	Ok(())
+}
+
+#[test]
+fn flush_and_count_per_chunk() -> Result<(), Error> {
+	let mut xml = XmlWriter::compact_mode(CountingWriter::default());
+	xml.set_buffer_size(1024);
+	xml.begin_elem("log")?;
+	xml.begin_elem("entry")?;
+	xml.text("first")?;
+	xml.end_elem()?;
+	assert_eq!(xml.flush_and_count()?, 25);
+	assert_eq!(xml.flush_and_count()?, 0);
+
+	// discarded output is not counted
+	let checkpoint = xml.checkpoint();
+	xml.empty_elem("discarded")?;
+	xml.rollback(checkpoint)?;
+	xml.begin_elem("entry")?;
+	xml.text("second")?;
+	xml.end_elem()?;
+	xml.end_elem()?;
+	assert_eq!(xml.flush_and_count()?, 27);
+	assert_eq!(xml.into_inner().buf, b"<log><entry>first</entry><entry>second</entry></log>");
+	Ok(())
+}
+
+#[test]
+fn slice_remaining_capacity() -> Result<(), Error> {
+	let mut storage = [0u8; 32];
+	let mut xml = XmlWriter::compact_mode(&mut storage[..]);
+	assert_eq!(xml.remaining_capacity(), Some(32));
+	xml.begin_elem("root")?;
+	assert_eq!(xml.remaining_capacity(), Some(27));
+	xml.begin_elem("a")?;
+	xml.text("text")?;
+	assert_eq!(xml.remaining_capacity(), Some(19));
+	xml.end_elem()?;
+	xml.end_elem()?;
+	assert_eq!(xml.remaining_capacity(), Some(8));
+	assert_eq!(xml.elem_text("b", "too long"), Err(Error::BufferFull));
+	assert_eq!(xml.into_inner().remaining(), Some(0));
+	assert_eq!(&storage[..24], b"<root><a>text</a></root>");
+
+	// pending output is taken into account, unbounded sinks have no limit
+	let mut storage = [0u8; 32];
+	let mut xml = XmlWriter::compact_mode(&mut storage[..]);
+	xml.set_buffer_size(16);
+	xml.begin_elem("root")?;
+	assert_eq!(xml.remaining_capacity(), Some(27));
+	assert_eq!(XmlWriter::compact_mode(Vec::new()).remaining_capacity(), None);
+	Ok(())
+}